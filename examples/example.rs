@@ -1,15 +1,18 @@
-use rayon::prelude::*;
 use std::{
-    io::{stderr, Write},
+    fs::File,
+    io::{stderr, stdout, Write},
     sync::Arc,
 };
 
 use rtrcrs::{
     camera::Camera,
-    color::{anti_aliased, Color},
-    definitions::{random_double, random_scene},
+    color::Color,
+    definitions::random_scene,
+    denoiser::Denoiser,
+    exr::{ExrImage, SampleFormat},
     material::{Dielectric, Lambertian, Metal},
     ray::Point3,
+    renderer::{AdaptiveSampling, Renderer},
     sphere::Sphere,
     Vec3,
 };
@@ -17,8 +20,8 @@ use rtrcrs::{
 fn main() {
     // Image
     const ASPECT_RATIO: f64 = 16.0 / 9.0;
-    const IMAGE_WIDTH: i32 = 400;
-    const IMAGE_HEIGHT: i32 = (IMAGE_WIDTH as f64 / ASPECT_RATIO) as i32;
+    const IMAGE_WIDTH: usize = 400;
+    const IMAGE_HEIGHT: usize = (IMAGE_WIDTH as f64 / ASPECT_RATIO) as usize;
     const SAMPLES_PER_PIXEL: i32 = 100;
     const MAX_DEPTH: i32 = 50;
 
@@ -57,47 +60,72 @@ fn main() {
     )));
 
     // Camera
-    let camera = Camera::new(
-        &Point3::new(13.0, 2.0, 3.0),
-        &Point3::new(0.0, 0.0, 0.0),
-        &Vec3::new(0.0, 1.0, 0.0),
-        20.0,
-        ASPECT_RATIO,
-        0.1,
-        10.0,
-    );
+    let camera = Camera::builder(Point3::new(13.0, 2.0, 3.0), Point3::new(0.0, 0.0, 0.0))
+        .vup(Vec3::new(0.0, 1.0, 0.0))
+        .vfov(20.0)
+        .aspect_ratio(ASPECT_RATIO)
+        .aperture(0.1)
+        .auto_focus(&world, 0.5, 0.5)
+        .build();
 
     // Render
-    println!("P3\n{} {}\n255", IMAGE_WIDTH, IMAGE_HEIGHT);
+    let spectral = std::env::args().any(|arg| arg == "--spectral");
+    let adaptive = std::env::args().any(|arg| arg == "--adaptive");
+    let aovs = std::env::args().any(|arg| arg == "--aovs");
+    let denoise = std::env::args().any(|arg| arg == "--denoise");
+    let hdr = std::env::args().any(|arg| arg == "--hdr");
+    let exr = std::env::args().any(|arg| arg == "--exr");
     std::env::set_var("RAYON_NUM_THREADS", "4");
-    let image = (0..IMAGE_HEIGHT)
-        .into_par_iter()
-        .rev()
-        .flat_map(|j| {
-            eprint!("\rScanlines remaining: {} ", j);
-            stderr().flush().unwrap();
-            (0..IMAGE_WIDTH)
-                .flat_map(|i| {
-                    let pixel_color: Color = (0..SAMPLES_PER_PIXEL)
-                        .map(|_| {
-                            let (u, v) = (
-                                (i as f64 + random_double(0.0, 1.0)) / (IMAGE_WIDTH - 1) as f64,
-                                (j as f64 + random_double(0.0, 1.0)) / (IMAGE_HEIGHT - 1) as f64,
-                            );
-                            camera.get_ray(u, v).color(&world, MAX_DEPTH)
-                        })
-                        .sum();
-                    anti_aliased(pixel_color, SAMPLES_PER_PIXEL)
-                        .iter()
-                        .map(|c| (c * 255.9) as u8)
-                        .collect::<Vec<u8>>()
-                })
-                .collect::<Vec<u8>>()
-        })
-        .collect::<Vec<u8>>();
+    let mut renderer = Renderer::new(IMAGE_WIDTH, IMAGE_HEIGHT, SAMPLES_PER_PIXEL, MAX_DEPTH)
+        .spectral(spectral)
+        .progress(true);
+    if adaptive {
+        renderer = renderer.adaptive(AdaptiveSampling {
+            threshold: 0.01,
+            max_samples_per_pixel: 4 * SAMPLES_PER_PIXEL,
+        });
+    }
+    let (image, aov_buffer) = if aovs || denoise {
+        let (image, aov_buffer) = renderer.render_with_aovs(&camera, &world);
+        (image, Some(aov_buffer))
+    } else {
+        (renderer.render(&camera, &world), None)
+    };
 
-    for pc in image.chunks(3) {
-        println!("{} {} {}", pc[0], pc[1], pc[2]);
+    match &aov_buffer {
+        Some(aov_buffer) if denoise => Denoiser::new()
+            .denoise(&image, aov_buffer)
+            .write_ppm(&mut stdout().lock())
+            .unwrap(),
+        _ => image.write_ppm(&mut stdout().lock()).unwrap(),
+    }
+    if hdr {
+        image
+            .write_hdr(&mut File::create("image.hdr").unwrap())
+            .unwrap();
+    }
+    if exr {
+        let mut layers = ExrImage::from_framebuffer(&image, SampleFormat::Float);
+        if let Some(aov_buffer) = aov_buffer.as_ref().filter(|_| aovs) {
+            layers = layers.with_aovs(aov_buffer);
+        }
+        layers
+            .write(&mut File::create("image.exr").unwrap())
+            .unwrap();
+    }
+    if let Some(aov_buffer) = aov_buffer.filter(|_| aovs) {
+        for (name, aov) in [
+            ("albedo.ppm", aov_buffer.albedo_image()),
+            ("normal.ppm", aov_buffer.normal_image()),
+            ("depth.ppm", aov_buffer.depth_image()),
+            ("object_id.ppm", aov_buffer.object_id_image()),
+        ] {
+            aov.write_ppm(&mut File::create(name).unwrap()).unwrap();
+        }
+    }
+    if adaptive {
+        let mut heatmap = File::create("sample_count.ppm").unwrap();
+        image.sample_heatmap().write_ppm(&mut heatmap).unwrap();
     }
 
     eprintln!("\rImage Generated.");
@@ -0,0 +1,86 @@
+use crate::{Point3, Ray, Vec3};
+
+/// Defines an axis aligned bounding box, used to skip objects a ray cannot hit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Point3,
+    pub max: Point3,
+}
+
+impl Aabb {
+    /// Creates the smallest box containing both corners, in any order.
+    pub fn new(a: Point3, b: Point3) -> Self {
+        Self {
+            min: a.inf(&b),
+            max: a.sup(&b),
+        }
+    }
+
+    /// Creates the smallest box containing every point.
+    pub fn from_points(points: impl IntoIterator<Item = Point3>) -> Option<Self> {
+        points.into_iter().fold(None, |bbox: Option<Aabb>, p| {
+            let point = Aabb::new(p, p);
+            Some(bbox.map_or(point, |bbox| bbox.surrounding(&point)))
+        })
+    }
+
+    /// Creates the smallest box containing the circle of the given `radius` centred on `center`
+    /// and facing `normal`.
+    pub fn around_circle(center: Point3, normal: &Vec3, radius: f64) -> Self {
+        let normal = normal.normalize();
+        let extent = normal.map(|n| radius * (1.0 - n * n).max(0.0).sqrt());
+        Aabb {
+            min: center - extent,
+            max: center + extent,
+        }
+    }
+
+    /// Returns the smallest box containing both boxes.
+    pub fn surrounding(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: self.min.inf(&other.min),
+            max: self.max.sup(&other.max),
+        }
+    }
+
+    /// Returns the same box grown by `margin` on every side.
+    pub fn padded(&self, margin: f64) -> Aabb {
+        let margin = Vec3::new(margin, margin, margin);
+        Aabb {
+            min: self.min - margin,
+            max: self.max + margin,
+        }
+    }
+
+    /// Returns the centre of the box.
+    pub fn centroid(&self) -> Point3 {
+        (self.min + self.max) / 2.0
+    }
+
+    /// Returns the area of the surface of the box.
+    pub fn surface_area(&self) -> f64 {
+        let d = self.max - self.min;
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    /// Returns the distance along `ray` at which it enters the box, if it does so before `t_max`
+    /// and leaves it after `t_min`.
+    pub fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<f64> {
+        let (mut t0, mut t1) = (t_min, t_max);
+        for axis in 0..3 {
+            let inv_d = 1.0 / ray.direction()[axis];
+            let mut near = (self.min[axis] - ray.origin()[axis]) * inv_d;
+            let mut far = (self.max[axis] - ray.origin()[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut near, &mut far);
+            }
+            // Comparisons written so that NaNs, from rays in the plane of a face, keep the bounds.
+            t0 = if near > t0 { near } else { t0 };
+            t1 = if far < t1 { far } else { t1 };
+            if t1 < t0 {
+                return None;
+            }
+        }
+        Some(t0)
+    }
+}
@@ -0,0 +1,193 @@
+use crate::{framebuffer::FrameBuffer, Color, HitRecord, Point3, Ray, Vec3};
+
+/// Defines the auxiliary buffers (arbitrary output variables) describing what the camera sees
+/// first in each pixel, for compositing and denoising. Every buffer is a plain average over the
+/// samples of a pixel, depth and position only over the samples that hit something.
+#[derive(Clone)]
+pub struct AovBuffer {
+    width: usize,
+    height: usize,
+    samples: Vec<u32>,
+    hits: Vec<u32>,
+    albedo: Vec<Color>,
+    normal: Vec<Vec3>,
+    position: Vec<Point3>,
+    depth: Vec<f64>,
+    object_id: Vec<Option<usize>>,
+    material_id: Vec<Option<usize>>,
+}
+
+impl AovBuffer {
+    /// Creates empty buffers of the given size.
+    pub fn new(width: usize, height: usize) -> Self {
+        let zero = Vec3::new(0.0, 0.0, 0.0);
+        Self {
+            width,
+            height,
+            samples: vec![0; width * height],
+            hits: vec![0; width * height],
+            albedo: vec![zero; width * height],
+            normal: vec![zero; width * height],
+            position: vec![zero; width * height],
+            depth: vec![0.0; width * height],
+            object_id: vec![None; width * height],
+            material_id: vec![None; width * height],
+        }
+    }
+
+    /// Returns the width of the buffers in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the buffers in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        y * self.width + x
+    }
+
+    /// Records what the camera `ray` of a sample taken in pixel (`x`, `y`) hit first, along with
+    /// the identifier of its material if it has one. Rays that miss everything take the background
+    /// as albedo.
+    pub fn add_sample(
+        &mut self,
+        x: usize,
+        y: usize,
+        ray: &Ray,
+        rec: Option<&HitRecord>,
+        material_id: Option<usize>,
+    ) {
+        let i = self.index(x, y);
+        self.samples[i] += 1;
+        match rec {
+            Some(rec) => {
+                self.hits[i] += 1;
+                self.albedo[i] += rec.material.albedo(rec);
+                self.normal[i] += rec.normal;
+                self.position[i] += rec.point;
+                self.depth[i] += rec.t * ray.direction().norm();
+                if self.object_id[i].is_none() {
+                    self.object_id[i] = Some(rec.object_id);
+                    self.material_id[i] = material_id;
+                }
+            }
+            None => self.albedo[i] += ray.background(),
+        }
+    }
+
+    /// Sums the samples of two sets of buffers of the same size.
+    pub fn merge(mut self, other: AovBuffer) -> Self {
+        for i in 0..self.samples.len() {
+            self.samples[i] += other.samples[i];
+            self.hits[i] += other.hits[i];
+            self.albedo[i] += other.albedo[i];
+            self.normal[i] += other.normal[i];
+            self.position[i] += other.position[i];
+            self.depth[i] += other.depth[i];
+            if self.object_id[i].is_none() {
+                self.object_id[i] = other.object_id[i];
+                self.material_id[i] = other.material_id[i];
+            }
+        }
+        self
+    }
+
+    /// Returns the average albedo of the surfaces seen in pixel (`x`, `y`).
+    pub fn albedo(&self, x: usize, y: usize) -> Color {
+        let i = self.index(x, y);
+        average(self.albedo[i], self.samples[i])
+    }
+
+    /// Returns the average shading normal seen in pixel (`x`, `y`), shorter than one along edges
+    /// and zero where nothing is hit.
+    pub fn normal(&self, x: usize, y: usize) -> Vec3 {
+        let i = self.index(x, y);
+        average(self.normal[i], self.samples[i])
+    }
+
+    /// Returns the average world position seen in pixel (`x`, `y`), if anything is hit.
+    pub fn position(&self, x: usize, y: usize) -> Option<Point3> {
+        let i = self.index(x, y);
+        (self.hits[i] > 0).then(|| self.position[i] / self.hits[i] as f64)
+    }
+
+    /// Returns the average distance from the camera to the surfaces seen in pixel (`x`, `y`), if
+    /// anything is hit.
+    pub fn depth(&self, x: usize, y: usize) -> Option<f64> {
+        let i = self.index(x, y);
+        (self.hits[i] > 0).then(|| self.depth[i] / self.hits[i] as f64)
+    }
+
+    /// Returns the index, in the world's [HittableList](crate::HittableList), of the object seen
+    /// in pixel (`x`, `y`).
+    pub fn object_id(&self, x: usize, y: usize) -> Option<usize> {
+        self.object_id[self.index(x, y)]
+    }
+
+    /// Returns the identifier of the material seen in pixel (`x`, `y`), see
+    /// [Renderer::material_ids](crate::renderer::Renderer::material_ids).
+    pub fn material_id(&self, x: usize, y: usize) -> Option<usize> {
+        self.material_id[self.index(x, y)]
+    }
+
+    /// Returns a viewable image built from one value per pixel.
+    fn image(&self, value: impl Fn(usize, usize) -> Color) -> FrameBuffer {
+        let mut image = FrameBuffer::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                image.add_sample(x, y, value(x, y));
+            }
+        }
+        image
+    }
+
+    /// Returns the albedo buffer as an image.
+    pub fn albedo_image(&self) -> FrameBuffer {
+        self.image(|x, y| self.albedo(x, y))
+    }
+
+    /// Returns the normal buffer as an image, each component mapped from [-1, 1] to [0, 1].
+    pub fn normal_image(&self) -> FrameBuffer {
+        self.image(|x, y| (self.normal(x, y) + Vec3::new(1.0, 1.0, 1.0)) / 2.0)
+    }
+
+    /// Returns the depth buffer as a grey image, white being closest and black furthest or empty.
+    pub fn depth_image(&self) -> FrameBuffer {
+        let depths = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .filter_map(|(x, y)| self.depth(x, y));
+        let max = depths.fold(0.0, f64::max);
+        self.image(|x, y| match self.depth(x, y) {
+            Some(depth) if max > 0.0 => Color::new(1.0, 1.0, 1.0) * (1.0 - depth / max),
+            _ => Color::new(0.0, 0.0, 0.0),
+        })
+    }
+
+    /// Returns the object ID buffer as an image, one arbitrary flat colour per object.
+    pub fn object_id_image(&self) -> FrameBuffer {
+        self.image(|x, y| match self.object_id(x, y) {
+            Some(id) => id_color(id),
+            None => Color::new(0.0, 0.0, 0.0),
+        })
+    }
+}
+
+/// Returns the average of `count` summed vectors, zero if there are none.
+fn average(sum: Vec3, count: u32) -> Vec3 {
+    if count > 0 {
+        sum / count as f64
+    } else {
+        Vec3::new(0.0, 0.0, 0.0)
+    }
+}
+
+/// Returns a colour that differs strongly between consecutive identifiers.
+fn id_color(id: usize) -> Color {
+    let mut h = (id as u64).wrapping_add(1).wrapping_mul(0x9e3779b97f4a7c15);
+    h ^= h >> 29;
+    let channel = |shift: u64| 0.2 + 0.8 * ((h >> shift) & 0xff) as f64 / 255.0;
+    Color::new(channel(0), channel(8), channel(16))
+}
@@ -0,0 +1,149 @@
+use std::sync::Arc;
+
+use crate::{aabb::Aabb, hittable::nearest, HitRecord, Hittable, Ray, INFINITY};
+
+/// Most objects stored in a single leaf.
+const LEAF_SIZE: usize = 4;
+
+/// Defines a node of the hierarchy, either a few objects or two smaller nodes.
+enum Node {
+    Leaf {
+        bbox: Aabb,
+        objects: Vec<Arc<dyn Hittable>>,
+    },
+    Split {
+        bbox: Aabb,
+        children: Box<[Node; 2]>,
+    },
+}
+
+impl Node {
+    /// Builds the hierarchy over objects that all have a bounding box, splitting them along the
+    /// axis and position that minimise the surface area heuristic.
+    fn build(mut objects: Vec<(Aabb, Arc<dyn Hittable>)>) -> Node {
+        let bbox = objects
+            .iter()
+            .skip(1)
+            .fold(objects[0].0, |bbox, (b, _)| bbox.surrounding(b));
+        if objects.len() <= LEAF_SIZE {
+            return Node::Leaf {
+                bbox,
+                objects: objects.into_iter().map(|(_, object)| object).collect(),
+            };
+        }
+
+        let centroids = Aabb::from_points(objects.iter().map(|(b, _)| b.centroid())).unwrap();
+        let extent = centroids.max - centroids.min;
+        let axis = extent.imax();
+        objects.sort_by(|a, b| a.0.centroid()[axis].total_cmp(&b.0.centroid()[axis]));
+
+        // Cost of every split position, from the areas of the boxes on either side.
+        let n = objects.len();
+        let mut right_areas = vec![0.0; n];
+        let mut right = objects[n - 1].0;
+        for i in (1..n).rev() {
+            right = right.surrounding(&objects[i].0);
+            right_areas[i] = right.surface_area();
+        }
+        let mut left = objects[0].0;
+        let (mut best, mut best_cost) = (n / 2, f64::INFINITY);
+        for i in 1..n {
+            left = left.surrounding(&objects[i - 1].0);
+            let cost = left.surface_area() * i as f64 + right_areas[i] * (n - i) as f64;
+            if cost < best_cost {
+                best = i;
+                best_cost = cost;
+            }
+        }
+
+        let right = objects.split_off(best);
+        Node::Split {
+            bbox,
+            children: Box::new([Node::build(objects), Node::build(right)]),
+        }
+    }
+
+    fn bbox(&self) -> &Aabb {
+        match self {
+            Node::Leaf { bbox, .. } | Node::Split { bbox, .. } => bbox,
+        }
+    }
+
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        match self {
+            Node::Leaf { objects, .. } => objects.iter().fold(None, |best, object| {
+                let t_max = best.as_ref().map_or(t_max, |rec: &HitRecord| rec.t);
+                nearest(best, object.hit(ray, t_min, t_max))
+            }),
+            Node::Split { children, .. } => {
+                // Visits the nearer child first, so that the other one can often be skipped.
+                let mut order = [&children[0], &children[1]]
+                    .map(|child| (child.bbox().hit(ray, t_min, t_max), child));
+                if order[1].0.unwrap_or(INFINITY) < order[0].0.unwrap_or(INFINITY) {
+                    order.swap(0, 1);
+                }
+                order.iter().fold(None, |best, (t_enter, child)| {
+                    let t_max = best.as_ref().map_or(t_max, |rec: &HitRecord| rec.t);
+                    match t_enter {
+                        Some(t_enter) if *t_enter <= t_max => {
+                            nearest(best, child.hit(ray, t_min, t_max))
+                        }
+                        _ => best,
+                    }
+                })
+            }
+        }
+    }
+}
+
+/// Defines a bounding volume hierarchy: objects grouped into nested boxes, so that a ray is only
+/// tested against the few objects whose boxes it goes through. Objects without a bounding box,
+/// such as planes, are tested one by one.
+pub struct Bvh {
+    root: Option<Node>,
+    unbounded: Vec<Arc<dyn Hittable>>,
+}
+
+impl Bvh {
+    pub fn new(objects: Vec<Arc<dyn Hittable>>) -> Self {
+        let (bounded, unbounded): (Vec<_>, Vec<_>) = objects
+            .into_iter()
+            .map(|object| (object.bounding_box(), object))
+            .partition(|(bbox, _)| bbox.is_some());
+        let bounded = bounded
+            .into_iter()
+            .map(|(bbox, object)| (bbox.unwrap(), object))
+            .collect::<Vec<_>>();
+        Self {
+            root: (!bounded.is_empty()).then(|| Node::build(bounded)),
+            unbounded: unbounded.into_iter().map(|(_, object)| object).collect(),
+        }
+    }
+}
+
+impl Hittable for Bvh {
+    /// Provides a definition of hit() for hierarchies, the nearest hit among their objects.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let best = self.unbounded.iter().fold(None, |best, object| {
+            let t_max = best.as_ref().map_or(t_max, |rec: &HitRecord| rec.t);
+            nearest(best, object.hit(ray, t_min, t_max))
+        });
+        match &self.root {
+            Some(root) => {
+                let t_max = best.as_ref().map_or(t_max, |rec| rec.t);
+                if root.bbox().hit(ray, t_min, t_max).is_none() {
+                    return best;
+                }
+                nearest(best, root.hit(ray, t_min, t_max))
+            }
+            None => best,
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        match self.root {
+            Some(ref root) if self.unbounded.is_empty() => Some(*root.bbox()),
+            _ => None,
+        }
+    }
+}
@@ -1,7 +1,203 @@
+use nalgebra::{Rotation3, Unit};
+
 use crate::{
-    definitions::{degrees_to_radians, random_in_unit_sphere},
-    Point3, Ray, Vec3,
+    definitions::{degrees_to_radians, random_in_unit_disk, sample_1d, sample_2d, INFINITY, PI},
+    Hittable, Point3, Ray, Vec3,
 };
+
+/// Defines the interface shared by every camera projection, mapping a point on the image to a Ray.
+pub trait CameraModel: Sync + Send {
+    /// Used to get the Ray through (`s`, `t`), both going from 0.0 to 1.0 across the image,
+    /// starting at its bottom left corner. Returns None for points of the image the projection
+    /// does not cover, which stay black.
+    fn try_get_ray(&self, s: f64, t: f64) -> Option<Ray>;
+}
+
+/// Returns the orthonormal basis (u, v, w) of a camera at `position` looking at `focus`, `u`
+/// pointing right, `v` up and `w` backwards.
+fn look_at_basis(position: &Point3, focus: &Point3, vup: &Vec3) -> (Vec3, Vec3, Vec3) {
+    let w = (position - focus).normalize();
+    let u = vup.cross(&w).normalize();
+    let v = w.cross(&u);
+    (u, v, w)
+}
+/// Defines the shape of the lens opening, which gives out of focus highlights (bokeh) their shape.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Aperture {
+    Circular,
+    /// A regular polygon formed by `blades` straight diaphragm blades, rotated by `rotation`
+    /// degrees. Fewer than 3 blades cannot form a polygon and give a circular opening.
+    Bladed {
+        blades: u32,
+        rotation: f64,
+    },
+}
+
+impl Aperture {
+    /// Returns a random point of the aperture, scaled to fit the unit circle on the XY plane.
+    fn sample(&self) -> Vec3 {
+        match *self {
+            Aperture::Bladed { blades, rotation } if blades >= 3 => {
+                // Pick one of the triangles fanning out from the centre, then a point inside it.
+                let step = 2.0 * PI / blades as f64;
+                let blade = ((sample_1d() * blades as f64) as u32).min(blades - 1);
+                let start = degrees_to_radians(rotation) + step * blade as f64;
+                let corner = |angle: f64| Vec3::new(angle.cos(), angle.sin(), 0.0);
+                let (mut a, mut b) = sample_2d();
+                if a + b > 1.0 {
+                    a = 1.0 - a;
+                    b = 1.0 - b;
+                }
+                a * corner(start) + b * corner(start + step)
+            }
+            _ => random_in_unit_disk(),
+        }
+    }
+}
+
+/// Defines the settings a [Camera](Camera) is built from, with helpers to aim and move it.
+#[derive(Clone, Copy)]
+pub struct CameraBuilder {
+    position: Point3,
+    focus: Point3,
+    vup: Vec3,
+    vfov: f64,
+    aspect_ratio: f64,
+    aperture: f64,
+    focus_dist: f64,
+    aperture_shape: Aperture,
+}
+
+impl CameraBuilder {
+    /// Starts from a pinhole Camera at `position` looking at `focus`, with a vertical FOV of 90°, a
+    /// 16:9 aspect ratio and the focus distance set to the distance between the two points.
+    pub fn new(position: Point3, focus: Point3) -> Self {
+        Self {
+            position,
+            focus,
+            vup: Vec3::new(0.0, 1.0, 0.0),
+            vfov: 90.0,
+            aspect_ratio: 16.0 / 9.0,
+            aperture: 0.0,
+            focus_dist: (position - focus).norm(),
+            aperture_shape: Aperture::Circular,
+        }
+    }
+
+    /// Sets the direction that appears upwards in the image.
+    pub fn vup(mut self, vup: Vec3) -> Self {
+        self.vup = vup;
+        self
+    }
+
+    /// Sets the vertical field of view, in degrees.
+    pub fn vfov(mut self, vfov: f64) -> Self {
+        self.vfov = vfov;
+        self
+    }
+
+    /// Sets the ratio of the image width to its height.
+    pub fn aspect_ratio(mut self, aspect_ratio: f64) -> Self {
+        self.aspect_ratio = aspect_ratio;
+        self
+    }
+
+    /// Sets the diameter of the lens, 0.0 keeping everything in focus.
+    pub fn aperture(mut self, aperture: f64) -> Self {
+        self.aperture = aperture;
+        self
+    }
+
+    /// Sets the shape of the lens opening.
+    pub fn aperture_shape(mut self, aperture_shape: Aperture) -> Self {
+        self.aperture_shape = aperture_shape;
+        self
+    }
+
+    /// Sets the distance, along the view direction, of the plane in perfect focus.
+    pub fn focus_dist(mut self, focus_dist: f64) -> Self {
+        self.focus_dist = focus_dist;
+        self
+    }
+
+    /// Sets the focus distance to the first object of `world` seen through (`s`, `t`), both going
+    /// from 0.0 to 1.0 across the image. The focus distance is left as is if nothing is hit.
+    pub fn auto_focus(self, world: &dyn Hittable, s: f64, t: f64) -> Self {
+        let pinhole = self.aperture(0.0).focus_dist(1.0).build();
+        let ray = pinhole.get_ray(s, t);
+        match world.hit(&ray, 0.001, INFINITY) {
+            Some(rec) => {
+                let (_, _, w) = look_at_basis(&self.position, &self.focus, &self.vup);
+                self.focus_dist((rec.point - self.position).dot(&-w))
+            }
+            None => self,
+        }
+    }
+
+    /// Turns the Camera to look at `focus`, without moving it.
+    pub fn look_at(mut self, focus: Point3) -> Self {
+        self.focus = focus;
+        self
+    }
+
+    /// Moves the Camera to `position`, still looking at the same point.
+    pub fn move_to(mut self, position: Point3) -> Self {
+        self.position = position;
+        self
+    }
+
+    /// Moves the Camera around the point it looks at, by `yaw` degrees around the up direction and
+    /// `pitch` degrees upwards. The pitch stops short of the poles, so the view never flips.
+    pub fn orbit(mut self, yaw: f64, pitch: f64) -> Self {
+        let up = Unit::new_normalize(self.vup);
+        let offset =
+            Rotation3::from_axis_angle(&up, degrees_to_radians(yaw)) * (self.position - self.focus);
+
+        let elevation = (offset.normalize().dot(&up)).clamp(-1.0, 1.0).asin();
+        let limit = degrees_to_radians(89.0);
+        let pitch = (elevation + degrees_to_radians(pitch)).clamp(-limit, limit) - elevation;
+        let (u, _, _) = look_at_basis(&(self.focus + offset), &self.focus, &self.vup);
+        let offset = Rotation3::from_axis_angle(&Unit::new_normalize(-u), pitch) * offset;
+
+        self.position = self.focus + offset;
+        self
+    }
+
+    /// Moves the Camera and the point it looks at by `distance` along the view direction.
+    pub fn dolly(mut self, distance: f64) -> Self {
+        let forward = (self.focus - self.position).normalize();
+        self.position += distance * forward;
+        self.focus += distance * forward;
+        self
+    }
+
+    /// Computes the geometry of the Camera.
+    pub fn build(&self) -> Camera {
+        let theta = degrees_to_radians(self.vfov);
+        let h = (theta / 2.0).tan();
+        let viewport_height = 2.0 * h;
+        let viewport_width = self.aspect_ratio * viewport_height;
+
+        let (u, v, w) = look_at_basis(&self.position, &self.focus, &self.vup);
+        let lens_radius = self.aperture / 2.0;
+        let origin = self.position;
+        let horizontal = self.focus_dist * viewport_width * u;
+        let vertical = self.focus_dist * viewport_height * v;
+        let lower_left_corner = origin - horizontal / 2.0 - vertical / 2.0 - w * self.focus_dist;
+        Camera {
+            origin,
+            horizontal,
+            vertical,
+            lower_left_corner,
+            u,
+            v,
+            lens_radius,
+            aperture_shape: self.aperture_shape,
+            settings: *self,
+        }
+    }
+}
+
 /// Defines a data-structure used to store the geometry of the Camera.
 #[derive(Clone, Copy)]
 pub struct Camera {
@@ -12,6 +208,8 @@ pub struct Camera {
     u: Vec3,
     v: Vec3,
     lens_radius: f64,
+    aperture_shape: Aperture,
+    settings: CameraBuilder,
 }
 
 impl Camera {
@@ -25,32 +223,86 @@ impl Camera {
         aperture: f64,
         focus_dist: f64,
     ) -> Self {
-        let theta = degrees_to_radians(vfov);
-        let h = (theta / 2.0).tan();
-        let viewport_height = 2.0 * h;
-        let viewport_width = aspect_ratio * viewport_height;
-
-        let w = (position - focus).normalize();
-        let u = vup.cross(&w).normalize();
-        let v = w.cross(&u);
-        let lens_radius = aperture / 2.0;
-        let origin = *position;
-        let horizontal = focus_dist * viewport_width * u;
-        let vertical = focus_dist * viewport_height * v;
-        let lower_left_corner = origin - horizontal / 2.0 - vertical / 2.0 - w * focus_dist;
-        Self {
-            origin,
-            horizontal,
-            vertical,
-            lower_left_corner,
-            u,
-            v,
-            lens_radius,
-        }
+        CameraBuilder::new(*position, *focus)
+            .vup(*vup)
+            .vfov(vfov)
+            .aspect_ratio(aspect_ratio)
+            .aperture(aperture)
+            .focus_dist(focus_dist)
+            .build()
+    }
+
+    /// Used to start building a Camera at `position` looking at `focus`.
+    pub fn builder(position: Point3, focus: Point3) -> CameraBuilder {
+        CameraBuilder::new(position, focus)
+    }
+
+    /// Used to set up the Camera in the units of a real one. The sensor size and focal length are
+    /// given in millimetres while world units, including `focus_dist`, are taken as metres.
+    ///
+    /// Panics if the sensor width, focal length or f-number is not positive.
+    #[allow(clippy::too_many_arguments)]
+    pub fn physical(
+        position: &Point3,
+        focus: &Point3,
+        vup: &Vec3,
+        sensor_width: f64,
+        focal_length: f64,
+        f_stop: f64,
+        focus_dist: f64,
+        aspect_ratio: f64,
+    ) -> Self {
+        assert!(sensor_width > 0.0, "sensor width must be positive");
+        assert!(focal_length > 0.0, "focal length must be positive");
+        assert!(f_stop > 0.0, "f-number must be positive");
+        let sensor_height = sensor_width / aspect_ratio;
+        let vfov = 2.0 * (sensor_height / (2.0 * focal_length)).atan() * 180.0 / PI;
+        let aperture = focal_length / f_stop / 1000.0;
+        Self::new(
+            position,
+            focus,
+            vup,
+            vfov,
+            aspect_ratio,
+            aperture,
+            focus_dist,
+        )
+    }
+
+    /// Used to change the shape of the lens opening.
+    pub fn with_aperture_shape(self, aperture_shape: Aperture) -> Self {
+        self.settings.aperture_shape(aperture_shape).build()
+    }
+
+    /// Returns the settings of the Camera, to derive new cameras from it.
+    pub fn settings(&self) -> CameraBuilder {
+        self.settings
+    }
+
+    /// Returns a copy of the Camera focused on the first object seen through (`s`, `t`).
+    pub fn auto_focus(&self, world: &dyn Hittable, s: f64, t: f64) -> Self {
+        self.settings.auto_focus(world, s, t).build()
     }
+
+    /// Returns a copy of the Camera turned to look at `focus`.
+    pub fn look_at(&self, focus: Point3) -> Self {
+        self.settings.look_at(focus).build()
+    }
+
+    /// Returns a copy of the Camera moved around the point it looks at, see
+    /// [CameraBuilder::orbit](CameraBuilder::orbit).
+    pub fn orbit(&self, yaw: f64, pitch: f64) -> Self {
+        self.settings.orbit(yaw, pitch).build()
+    }
+
+    /// Returns a copy of the Camera moved by `distance` along the view direction.
+    pub fn dolly(&self, distance: f64) -> Self {
+        self.settings.dolly(distance).build()
+    }
+
     /// Used to get the Ray corresponding to a Pixel and the Camera.
     pub fn get_ray(&self, s: f64, t: f64) -> Ray {
-        let rd = self.lens_radius * random_in_unit_sphere();
+        let rd = self.lens_radius * self.aperture_shape.sample();
         let offset = self.u * rd.x + self.v * rd.y;
         Ray::new(
             self.origin + offset,
@@ -58,3 +310,224 @@ impl Camera {
         )
     }
 }
+
+impl CameraModel for Camera {
+    fn try_get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        Some(self.get_ray(s, t))
+    }
+}
+
+/// Defines a camera casting parallel rays, as used for architectural elevations.
+#[derive(Clone, Copy)]
+pub struct OrthographicCamera {
+    lower_left_corner: Point3,
+    horizontal: Vec3,
+    vertical: Vec3,
+    direction: Vec3,
+}
+
+impl OrthographicCamera {
+    /// Used to set the geometric values of the Camera, `viewport_height` being the height of the
+    /// visible area in world units.
+    pub fn new(
+        position: &Point3,
+        focus: &Point3,
+        vup: &Vec3,
+        viewport_height: f64,
+        aspect_ratio: f64,
+    ) -> Self {
+        let (u, v, w) = look_at_basis(position, focus, vup);
+        let horizontal = aspect_ratio * viewport_height * u;
+        let vertical = viewport_height * v;
+        Self {
+            lower_left_corner: position - horizontal / 2.0 - vertical / 2.0,
+            horizontal,
+            vertical,
+            direction: -w,
+        }
+    }
+}
+
+impl CameraModel for OrthographicCamera {
+    fn try_get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        Some(Ray::new(
+            self.lower_left_corner + s * self.horizontal + t * self.vertical,
+            self.direction,
+        ))
+    }
+}
+
+/// Defines a camera covering the whole sphere of directions with an equirectangular projection, as
+/// used for 360° and VR panoramas. The image should have an aspect ratio of 2:1.
+#[derive(Clone, Copy)]
+pub struct EquirectangularCamera {
+    origin: Point3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+}
+
+impl EquirectangularCamera {
+    /// Used to set the geometric values of the Camera, `focus` being at the centre of the image.
+    pub fn new(position: &Point3, focus: &Point3, vup: &Vec3) -> Self {
+        let (u, v, w) = look_at_basis(position, focus, vup);
+        Self {
+            origin: *position,
+            u,
+            v,
+            w,
+        }
+    }
+}
+
+impl CameraModel for EquirectangularCamera {
+    fn try_get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        let longitude = (s - 0.5) * 2.0 * PI;
+        let latitude = (t - 0.5) * PI;
+        let direction = latitude.cos() * longitude.sin() * self.u + latitude.sin() * self.v
+            - latitude.cos() * longitude.cos() * self.w;
+        Some(Ray::new(self.origin, direction))
+    }
+}
+
+/// Defines a camera with an equidistant fisheye projection, the angle from the view direction
+/// growing linearly with the distance from the centre of the image. Points outside the circle
+/// inscribed in the image height get no ray.
+#[derive(Clone, Copy)]
+pub struct FisheyeCamera {
+    origin: Point3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    half_fov: f64,
+    aspect_ratio: f64,
+}
+
+impl FisheyeCamera {
+    /// Used to set the geometric values of the Camera, `fov` being the angle in degrees covered by
+    /// the circle inscribed in the image height.
+    pub fn new(position: &Point3, focus: &Point3, vup: &Vec3, fov: f64, aspect_ratio: f64) -> Self {
+        let (u, v, w) = look_at_basis(position, focus, vup);
+        Self {
+            origin: *position,
+            u,
+            v,
+            w,
+            half_fov: degrees_to_radians(fov) / 2.0,
+            aspect_ratio,
+        }
+    }
+}
+
+impl CameraModel for FisheyeCamera {
+    fn try_get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        let (x, y) = ((2.0 * s - 1.0) * self.aspect_ratio, 2.0 * t - 1.0);
+        let r = (x * x + y * y).sqrt();
+        if r > 1.0 {
+            return None;
+        }
+        let theta = (r * self.half_fov).min(PI);
+        let phi = y.atan2(x);
+        let direction =
+            theta.sin() * (phi.cos() * self.u + phi.sin() * self.v) - theta.cos() * self.w;
+        Some(Ray::new(self.origin, direction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{definitions::seed_random, sphere::Sphere, Color, HittableList, Lambertian};
+    use std::sync::Arc;
+
+    #[test]
+    fn auto_focus_test() {
+        let mut world = HittableList::default();
+        world.add(Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, -5.0),
+            1.0,
+            Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+        )));
+        let builder = Camera::builder(Point3::new(0.0, 0.0, 0.0), Point3::new(0.0, 0.0, -1.0))
+            .auto_focus(&world, 0.5, 0.5);
+
+        assert!((builder.focus_dist - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn orbit_test() {
+        let focus = Point3::new(1.0, 0.0, 0.0);
+        let builder = Camera::builder(Point3::new(1.0, 0.0, 3.0), focus).orbit(90.0, 0.0);
+
+        assert!((builder.position - Point3::new(4.0, 0.0, 0.0)).norm() < 1e-9);
+
+        let builder = builder.orbit(0.0, 120.0);
+        let elevation = (builder.position - focus).normalize().y.asin();
+
+        assert!(((builder.position - focus).norm() - 3.0).abs() < 1e-9);
+        assert!((elevation - degrees_to_radians(89.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn physical_test() {
+        let (position, focus) = (Point3::new(0.0, 0.0, 0.0), Point3::new(0.0, 0.0, -1.0));
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let settings =
+            Camera::physical(&position, &focus, &up, 36.0, 50.0, 2.0, 1.0, 1.5).settings();
+
+        // A 50 mm lens at f/2 has a 25 mm opening.
+        assert!((settings.aperture - 0.025).abs() < 1e-12);
+        // A 36 mm wide sensor at 3:2 is 24 mm high, seen from 50 mm away.
+        let vfov = 2.0 * (12.0f64 / 50.0).atan();
+        assert!((degrees_to_radians(settings.vfov) - vfov).abs() < 1e-12);
+    }
+
+    #[test]
+    #[should_panic(expected = "f-number must be positive")]
+    fn physical_f_stop_test() {
+        let (position, focus) = (Point3::new(0.0, 0.0, 0.0), Point3::new(0.0, 0.0, -1.0));
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        Camera::physical(&position, &focus, &up, 36.0, 50.0, 0.0, 1.0, 1.5);
+    }
+
+    #[test]
+    fn bladed_aperture_test() {
+        seed_random(7);
+        let (blades, rotation) = (5, 18.0);
+        let aperture = Aperture::Bladed { blades, rotation };
+        let corners = (0..=blades)
+            .map(|k| degrees_to_radians(rotation) + 2.0 * PI * k as f64 / blades as f64)
+            .map(|angle| Vec3::new(angle.cos(), angle.sin(), 0.0))
+            .collect::<Vec<Vec3>>();
+
+        // Every sample lies on the inner side of each edge of the pentagon.
+        for _ in 0..1000 {
+            let point = aperture.sample();
+            assert_eq!(point.z, 0.0);
+            assert!(corners
+                .windows(2)
+                .all(|edge| (edge[1] - edge[0]).cross(&(point - edge[0])).z >= -1e-12));
+        }
+    }
+
+    #[test]
+    fn fisheye_test() {
+        let camera = FisheyeCamera::new(
+            &Point3::new(0.0, 0.0, 0.0),
+            &Point3::new(0.0, 0.0, -1.0),
+            &Vec3::new(0.0, 1.0, 0.0),
+            180.0,
+            2.0,
+        );
+        let direction = |s, t| camera.try_get_ray(s, t).unwrap().direction().normalize();
+
+        assert!((direction(0.5, 0.5) - Vec3::new(0.0, 0.0, -1.0)).norm() < 1e-9);
+        // The edge of the circle looks sideways, at 90° from the view direction.
+        assert!((direction(0.75, 0.5) - Vec3::new(1.0, 0.0, 0.0)).norm() < 1e-9);
+        assert!((direction(0.5, 1.0) - Vec3::new(0.0, 1.0, 0.0)).norm() < 1e-9);
+        // Past it, in the corners and on the sides of the wide image, there is no ray.
+        assert!(camera.try_get_ray(0.8, 0.5).is_none());
+        assert!(camera.try_get_ray(0.0, 0.0).is_none());
+        assert!(camera.try_get_ray(1.0, 1.0).is_none());
+    }
+}
@@ -7,3 +7,8 @@ pub fn anti_aliased(color: Color, samples_per_pixel: i32) -> Color {
 
     Color::new(sample(color.x), sample(color.y), sample(color.z))
 }
+
+/// Returns the relative luminance of a linear RGB colour.
+pub fn luminance(color: &Color) -> f64 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
@@ -0,0 +1,137 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    definitions::{solve_quadratic, PI},
+    frame::{azimuth, Frame},
+    hittable::nearest,
+    plane::Disk,
+    HitRecord, Hittable, Material, Point3, Ray, Vec3,
+};
+
+/// Defines a circular cone closed by a disk at its base.
+pub struct Cone {
+    frame: Frame,
+    radius: f64,
+    height: f64,
+    base: Disk,
+    material: Arc<dyn Material>,
+}
+
+impl Cone {
+    /// Creates the cone whose base of the given `radius` is centred on `base` and whose tip is at
+    /// `apex`. Texture coordinates on the side are the angle around the axis, `u`, and the height,
+    /// `v`; on the base they are the same as on a [Disk].
+    pub fn new(base: Point3, apex: Point3, radius: f64, material: Arc<dyn Material>) -> Self {
+        let axis = apex - base;
+        Self {
+            frame: Frame::new(base, axis),
+            radius,
+            height: axis.norm(),
+            base: Disk::new(base, -axis, radius, material.clone()),
+            material,
+        }
+    }
+
+    /// Returns the hit with the side of the cone at `t`, if it lies between the base and the apex.
+    fn side_hit(&self, ray: &Ray, origin: &Point3, direction: &Vec3, t: f64) -> Option<HitRecord> {
+        let p = origin + t * direction;
+        if p.z < 0.0 || p.z > self.height {
+            return None;
+        }
+        // Gradient of x² + y² - (radius / height)² (height - z)².
+        let slope = self.radius / self.height;
+        let gradient = Vec3::new(p.x, p.y, slope * slope * (self.height - p.z));
+        let outward_normal = self.frame.from_local(&gradient.normalize());
+
+        let phi = 2.0 * PI * azimuth(&p);
+        let dpdu = 2.0 * PI * Vec3::new(-p.y, p.x, 0.0);
+        let dpdv = Vec3::new(
+            -self.radius * phi.cos(),
+            -self.radius * phi.sin(),
+            self.height,
+        );
+        let rec = HitRecord::new(ray, t, &outward_normal, self.material.clone());
+        Some(rec.with_uv(
+            (azimuth(&p), p.z / self.height),
+            (self.frame.from_local(&dpdu), self.frame.from_local(&dpdv)),
+        ))
+    }
+}
+
+impl Hittable for Cone {
+    /// Provides a definition of hit() for cones.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (o, d) = self.frame.ray_to_local(ray);
+        // Solves x² + y² = k² w², w being the distance to the apex along the axis.
+        let k2 = (self.radius / self.height).powi(2);
+        let (w, dw) = (self.height - o.z, -d.z);
+        let side = solve_quadratic(
+            d.x * d.x + d.y * d.y - k2 * dw * dw,
+            2.0 * (o.x * d.x + o.y * d.y - k2 * w * dw),
+            o.x * o.x + o.y * o.y - k2 * w * w,
+        )
+        .and_then(|(t0, t1)| {
+            [t0, t1]
+                .iter()
+                .filter(|t| (t_min..=t_max).contains(*t))
+                .find_map(|&t| self.side_hit(ray, &o, &d, t))
+        });
+
+        let t_max = side.as_ref().map_or(t_max, |rec| rec.t);
+        nearest(side, self.base.hit(ray, t_min, t_max))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let apex = self.frame.origin + self.height * self.frame.normal;
+        let base = Aabb::around_circle(self.frame.origin, &self.frame.normal, self.radius);
+        Some(base.surrounding(&Aabb::new(apex, apex)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+
+    #[test]
+    fn cone_hit_test() {
+        let material = Arc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
+        let cone = Cone::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, 2.0),
+            1.0,
+            material,
+        );
+
+        // Halfway up, the side is half as wide as the base and leans inwards.
+        let ray = Ray::new(Point3::new(-5.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 0.0));
+        let rec = cone.hit(&ray, 0.001, f64::MAX).unwrap();
+        assert!((rec.t - 4.5).abs() < 1e-9);
+        let normal = Vec3::new(-2.0, 0.0, 1.0).normalize();
+        assert!((rec.normal - normal).norm() < 1e-9);
+        assert!(rec.front_face);
+        assert!((rec.u - 0.5).abs() < 1e-9 && (rec.v - 0.5).abs() < 1e-9);
+        let rec = cone.hit(&ray, 4.6, f64::MAX).unwrap();
+        assert!((rec.t - 5.5).abs() < 1e-9);
+        assert!(!rec.front_face);
+
+        // From above, the side is hit where it is as wide as the ray is off the axis.
+        let down = Ray::new(Point3::new(0.2, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = cone.hit(&down, 0.001, f64::MAX).unwrap();
+        assert!((rec.t - 3.4).abs() < 1e-9);
+        assert!((rec.v - 0.8).abs() < 1e-9);
+
+        // The base closes the cone.
+        let up = Ray::new(Point3::new(0.2, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0));
+        let rec = cone.hit(&up, 0.001, f64::MAX).unwrap();
+        assert!((rec.t - 5.0).abs() < 1e-9);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, -1.0)).norm() < 1e-9);
+        assert!(rec.front_face);
+        assert!((rec.v - 0.2).abs() < 1e-9);
+
+        // The mirrored cone past the apex is not part of the shape.
+        let above = Ray::new(Point3::new(-5.0, 0.0, 3.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(cone.hit(&above, 0.001, f64::MAX).is_none());
+    }
+}
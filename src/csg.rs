@@ -0,0 +1,210 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    definitions::INFINITY,
+    hittable::{HitRecord, Hittable, Interval},
+    Ray,
+};
+
+/// Defines how the two operands of a [Csg] node are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsgOperation {
+    /// Everything inside either operand.
+    Union,
+    /// Everything inside both operands.
+    Intersection,
+    /// Everything inside the first operand but not the second.
+    Difference,
+}
+
+impl CsgOperation {
+    /// Returns whether a point is inside the result, given whether it is inside each operand.
+    fn contains(&self, first: bool, second: bool) -> bool {
+        match self {
+            CsgOperation::Union => first || second,
+            CsgOperation::Intersection => first && second,
+            CsgOperation::Difference => first && !second,
+        }
+    }
+}
+
+/// Returns the spans inside the combination of two solids, given the spans inside each of them.
+pub fn combine(first: &[Interval], second: &[Interval], operation: CsgOperation) -> Vec<Interval> {
+    // Every boundary as (record, whether it belongs to the first operand, whether it enters).
+    let mut events = first
+        .iter()
+        .map(|span| (span, true))
+        .chain(second.iter().map(|span| (span, false)))
+        .flat_map(|(span, is_first)| [(&span.enter, is_first, true), (&span.exit, is_first, false)])
+        .collect::<Vec<(&HitRecord, bool, bool)>>();
+    events.sort_by(|a, b| a.0.t.total_cmp(&b.0.t));
+
+    let (mut inside_first, mut inside_second) = (false, false);
+    let mut enter: Option<&HitRecord> = None;
+    let mut intervals = vec![];
+    for (rec, is_first, enters) in events {
+        if is_first {
+            inside_first = enters;
+        } else {
+            inside_second = enters;
+        }
+        match (enter, operation.contains(inside_first, inside_second)) {
+            (None, true) => enter = Some(rec),
+            (Some(start), false) => {
+                // The surfaces keep their normals facing the ray, only which side is the outside
+                // may change, such as where the second operand is carved out of the first.
+                intervals.push(Interval {
+                    enter: HitRecord {
+                        front_face: true,
+                        ..start.clone()
+                    },
+                    exit: HitRecord {
+                        front_face: false,
+                        ..rec.clone()
+                    },
+                });
+                enter = None;
+            }
+            _ => (),
+        }
+    }
+    intervals
+}
+
+/// Defines a solid built from two others by constructive solid geometry, whose surfaces keep the
+/// materials of the operand they come from. Both operands must be closed, or unbounded like a
+/// plane, which bounds the half-space behind it.
+pub struct Csg {
+    first: Arc<dyn Hittable>,
+    second: Arc<dyn Hittable>,
+    operation: CsgOperation,
+}
+
+impl Csg {
+    pub fn new(
+        first: Arc<dyn Hittable>,
+        second: Arc<dyn Hittable>,
+        operation: CsgOperation,
+    ) -> Self {
+        Self {
+            first,
+            second,
+            operation,
+        }
+    }
+
+    /// Creates the solid made of everything inside either operand.
+    pub fn union(first: Arc<dyn Hittable>, second: Arc<dyn Hittable>) -> Self {
+        Self::new(first, second, CsgOperation::Union)
+    }
+
+    /// Creates the solid made of everything inside both operands.
+    pub fn intersection(first: Arc<dyn Hittable>, second: Arc<dyn Hittable>) -> Self {
+        Self::new(first, second, CsgOperation::Intersection)
+    }
+
+    /// Creates the solid made of everything inside `first` but not inside `second`.
+    pub fn difference(first: Arc<dyn Hittable>, second: Arc<dyn Hittable>) -> Self {
+        Self::new(first, second, CsgOperation::Difference)
+    }
+}
+
+impl Hittable for Csg {
+    /// Provides a definition of hit() for combined solids, the nearest boundary of their spans.
+    /// Spans running off to infinity, out of unbounded operands, have no surface at that end.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.intervals(ray)
+            .into_iter()
+            .flat_map(|span| [span.enter, span.exit])
+            .filter(|rec| rec.t.abs() < INFINITY)
+            .find(|rec| t_min <= rec.t && rec.t <= t_max)
+    }
+
+    fn intervals(&self, ray: &Ray) -> Vec<Interval> {
+        let first = self.first.intervals(ray);
+        if first.is_empty() && self.operation != CsgOperation::Union {
+            return vec![];
+        }
+        combine(&first, &self.second.intervals(ray), self.operation)
+    }
+
+    /// Provides the box of the first operand, which contains the result unless it is a union.
+    fn bounding_box(&self) -> Option<Aabb> {
+        let first = self.first.bounding_box()?;
+        match self.operation {
+            CsgOperation::Union => Some(first.surrounding(&self.second.bounding_box()?)),
+            CsgOperation::Intersection | CsgOperation::Difference => Some(first),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{material::Lambertian, plane::Plane, sphere::Sphere, Color, Point3, Vec3};
+
+    fn sphere(x: f64, radius: f64) -> Arc<dyn Hittable> {
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        Arc::new(Sphere::new(Point3::new(x, 0.0, 0.0), radius, material))
+    }
+
+    #[test]
+    fn operations_test() {
+        let ray = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let bounds = |csg: Csg| {
+            csg.intervals(&ray)
+                .iter()
+                .map(|span| (span.enter.t, span.exit.t))
+                .collect::<Vec<(f64, f64)>>()
+        };
+
+        // Spheres spanning [-1, 1] and [0, 2] along the X axis.
+        let (a, b) = (sphere(0.0, 1.0), sphere(1.0, 1.0));
+        assert_eq!(bounds(Csg::union(a.clone(), b.clone())), vec![(4.0, 7.0)]);
+        assert_eq!(
+            bounds(Csg::intersection(a.clone(), b.clone())),
+            vec![(5.0, 6.0)]
+        );
+        assert_eq!(
+            bounds(Csg::difference(a.clone(), b.clone())),
+            vec![(4.0, 5.0)]
+        );
+        assert_eq!(
+            bounds(Csg::difference(a, sphere(0.0, 0.5))),
+            vec![(4.0, 4.5), (5.5, 6.0)]
+        );
+    }
+
+    #[test]
+    fn carved_hit_test() {
+        let ray = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let carved = Csg::difference(sphere(0.0, 1.0), sphere(-1.0, 0.5));
+
+        // The ray enters through the surface of the carved out sphere, facing it.
+        let rec = carved.hit(&ray, 0.001, f64::MAX).unwrap();
+        assert!((rec.t - 4.5).abs() < 1e-9);
+        assert!(rec.front_face);
+        assert!((rec.normal - Vec3::new(-1.0, 0.0, 0.0)).norm() < 1e-9);
+    }
+
+    #[test]
+    fn unbounded_test() {
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let ground = Arc::new(Plane::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            material,
+        ));
+        let carved = Csg::difference(ground, sphere(0.0, 1.0));
+
+        // From above, the ray goes through the hole to the bottom of the sphere.
+        let ray = Ray::new(Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let rec = carved.hit(&ray, 0.001, INFINITY).unwrap();
+        assert!((rec.t - 6.0).abs() < 1e-9);
+
+        // Starting below the ground and going down, the ray never leaves the solid.
+        let ray = Ray::new(Point3::new(3.0, -1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(carved.hit(&ray, 0.001, INFINITY).is_none());
+    }
+}
@@ -0,0 +1,357 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+    sync::Arc,
+};
+
+use crate::{aabb::Aabb, bvh::Bvh, frame::Frame, HitRecord, Hittable, Material, Point3, Ray, Vec3};
+
+/// Defines how the surface of a [Curve] is shaded. Both are intersected as a flat ribbon facing
+/// the ray, which is cheaper than an actual tube and indistinguishable on thin curves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CurveShape {
+    /// A flat strip facing the ray, such as a blade of grass seen from afar.
+    Ribbon,
+    /// A tube, its normal turning around the axis across the width of the ribbon.
+    Cylinder,
+}
+
+/// Returns the point at `u` of the cubic Bézier curve with control points `cp`.
+fn bezier(cp: &[Vec3; 4], u: f64) -> Vec3 {
+    let v = 1.0 - u;
+    v * v * v * cp[0] + 3.0 * v * v * u * cp[1] + 3.0 * v * u * u * cp[2] + u * u * u * cp[3]
+}
+
+/// Returns the derivative at `u` of the cubic Bézier curve with control points `cp`.
+fn bezier_derivative(cp: &[Vec3; 4], u: f64) -> Vec3 {
+    let v = 1.0 - u;
+    3.0 * (v * v * (cp[1] - cp[0]) + 2.0 * v * u * (cp[2] - cp[1]) + u * u * (cp[3] - cp[2]))
+}
+
+/// Returns the control points of both halves of a cubic Bézier curve, by de Casteljau's algorithm.
+fn split(cp: &[Vec3; 4]) -> [[Vec3; 4]; 2] {
+    let mid = |a: &Vec3, b: &Vec3| (a + b) / 2.0;
+    let (a, b, c) = (
+        mid(&cp[0], &cp[1]),
+        mid(&cp[1], &cp[2]),
+        mid(&cp[2], &cp[3]),
+    );
+    let (d, e) = (mid(&a, &b), mid(&b, &c));
+    let f = mid(&d, &e);
+    [[cp[0], a, d, f], [f, e, c, cp[3]]]
+}
+
+/// Defines a cubic Bézier curve swept by a width interpolated from its start to its end, used for
+/// hair, fur and grass. Texture coordinates are the position along the curve, `u`, and across
+/// it, `v`, `dpdu` follows the curve and `dpdv` spans its width.
+pub struct Curve {
+    control_points: [Point3; 4],
+    widths: (f64, f64),
+    /// The part of the original curve this one covers, once split.
+    u_range: (f64, f64),
+    shape: CurveShape,
+    material: Arc<dyn Material>,
+}
+
+impl Curve {
+    pub fn new(
+        control_points: [Point3; 4],
+        widths: (f64, f64),
+        material: Arc<dyn Material>,
+    ) -> Self {
+        Self {
+            control_points,
+            widths,
+            u_range: (0.0, 1.0),
+            shape: CurveShape::Cylinder,
+            material,
+        }
+    }
+
+    pub fn with_shape(mut self, shape: CurveShape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    /// Returns both halves of the curve, whose tighter boxes make it cheaper to find in a [Bvh].
+    fn halves(&self) -> [Curve; 2] {
+        let [first, second] = split(&self.control_points);
+        let width = self.width(0.5);
+        let u = (self.u_range.0 + self.u_range.1) / 2.0;
+        [
+            (first, (self.widths.0, width), (self.u_range.0, u)),
+            (second, (width, self.widths.1), (u, self.u_range.1)),
+        ]
+        .map(|(control_points, widths, u_range)| Curve {
+            control_points,
+            widths,
+            u_range,
+            shape: self.shape,
+            material: self.material.clone(),
+        })
+    }
+
+    /// Returns the width of the curve at `u`.
+    fn width(&self, u: f64) -> f64 {
+        self.widths.0 + (self.widths.1 - self.widths.0) * u
+    }
+
+    /// Returns the nearest hit with the part of the curve between `u.0` and `u.1`, whose control
+    /// points `cp` are in the frame of the ray, as its distance along the ray and the position
+    /// along the curve. Halves the curve `depth` times, skipping halves whose boxes the ray
+    /// misses, then treats what remains as a straight segment.
+    fn segment_hit(
+        &self,
+        cp: &[Vec3; 4],
+        u: (f64, f64),
+        depth: u32,
+        z_min: f64,
+        z_max: f64,
+    ) -> Option<(f64, f64)> {
+        let half_width = self.width(u.0).max(self.width(u.1)) / 2.0;
+        let bbox = Aabb::from_points(cp.iter().copied())?.padded(half_width);
+        if bbox.min.x > 0.0 || bbox.max.x < 0.0 || bbox.min.y > 0.0 || bbox.max.y < 0.0 {
+            return None;
+        }
+        if bbox.max.z < z_min || bbox.min.z > z_max {
+            return None;
+        }
+
+        if depth > 0 {
+            let [first, second] = split(cp);
+            let middle = (u.0 + u.1) / 2.0;
+            let hit = self.segment_hit(&first, (u.0, middle), depth - 1, z_min, z_max);
+            let z_max = hit.map_or(z_max, |(z, _)| z);
+            return match self.segment_hit(&second, (middle, u.1), depth - 1, z_min, z_max) {
+                None => hit,
+                closer => closer,
+            };
+        }
+
+        // The ray goes through the origin of this frame, which has to lie between the lines
+        // across both ends of the segment.
+        let start = cp[1].xy() - cp[0].xy();
+        let end = cp[2].xy() - cp[3].xy();
+        if start.dot(&-cp[0].xy()) < 0.0 || end.dot(&-cp[3].xy()) < 0.0 {
+            return None;
+        }
+        let direction = cp[3].xy() - cp[0].xy();
+        // A segment seen end on is hit at its start.
+        let w = match direction.norm_squared() {
+            length if length > 0.0 => (-cp[0].xy().dot(&direction) / length).clamp(0.0, 1.0),
+            _ => 0.0,
+        };
+        let point = bezier(cp, w);
+        let width = self.width(u.0 + (u.1 - u.0) * w);
+        if point.xy().norm() > width / 2.0 || point.z < z_min || point.z > z_max {
+            return None;
+        }
+        Some((point.z, u.0 + (u.1 - u.0) * w))
+    }
+}
+
+impl Hittable for Curve {
+    /// Provides a definition of hit() for curves, found in the frame of the ray.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let length = ray.direction().norm();
+        let frame = Frame::new(ray.origin(), ray.direction());
+        let cp = self
+            .control_points
+            .map(|p| frame.to_local(&(p - frame.origin)));
+
+        // Enough halvings for the segments to be flat to within a twentieth of the width.
+        let flatness = (0..2)
+            .map(|i| (cp[i] - 2.0 * cp[i + 1] + cp[i + 2]).abs().max())
+            .fold(0.0, f64::max);
+        let epsilon = self.widths.0.max(self.widths.1) / 20.0;
+        let depth = ((2f64.sqrt() * 6.0 * flatness / (8.0 * epsilon)).log2() / 2.0)
+            .ceil()
+            .clamp(0.0, 10.0) as u32;
+        let (z, u) = self.segment_hit(&cp, (0.0, 1.0), depth, t_min * length, t_max * length)?;
+
+        let t = z / length;
+        let point = ray.at(t);
+        let dpdu = bezier_derivative(&self.control_points, u);
+        let tangent = dpdu.try_normalize(1e-12).unwrap_or(frame.tangent);
+        // The ribbon faces the ray, its normal pointing back along the ray across the curve.
+        let towards_ray = -frame.normal;
+        let ribbon_normal = (towards_ray - towards_ray.dot(&tangent) * tangent)
+            .try_normalize(1e-12)
+            .unwrap_or(towards_ray);
+        let across = tangent.cross(&ribbon_normal);
+        let offset = (point - bezier(&self.control_points, u)).dot(&across) / (self.width(u) / 2.0);
+        let offset = offset.clamp(-1.0, 1.0);
+        let outward_normal = match self.shape {
+            CurveShape::Ribbon => ribbon_normal,
+            CurveShape::Cylinder => {
+                (1.0 - offset * offset).sqrt() * ribbon_normal + offset * across
+            }
+        };
+
+        let (u0, u1) = self.u_range;
+        let rec = HitRecord {
+            width: self.width(u),
+            ..HitRecord::new(ray, t, &outward_normal, self.material.clone())
+        };
+        Some(rec.with_uv(
+            (u0 + (u1 - u0) * u, (offset + 1.0) / 2.0),
+            (dpdu / (u1 - u0), self.width(u) * across),
+        ))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let bbox = Aabb::from_points(self.control_points.iter().copied())?;
+        Some(bbox.padded(self.widths.0.max(self.widths.1) / 2.0))
+    }
+}
+
+/// How many times every curve of a [CurveSet] is halved before going into its hierarchy.
+const SPLIT_DEPTH: usize = 3;
+
+/// Defines a set of curves held in a bounding volume hierarchy, such as the strands of a head of
+/// hair.
+pub struct CurveSet {
+    bvh: Bvh,
+}
+
+impl CurveSet {
+    pub fn new(curves: Vec<Curve>) -> Self {
+        let pieces = (0..SPLIT_DEPTH).fold(curves, |curves, _| {
+            curves.iter().flat_map(|curve| curve.halves()).collect()
+        });
+        Self {
+            bvh: Bvh::new(
+                pieces
+                    .into_iter()
+                    .map(|curve| Arc::new(curve) as Arc<dyn Hittable>)
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Reads strands from a text file of one strand per line: the coordinates of 3n + 1 control
+    /// points, making n Bézier curves joined end to end, then the widths at the root and at the
+    /// tip. Blank lines and lines starting with `#` are skipped.
+    pub fn read(
+        reader: impl BufRead,
+        shape: CurveShape,
+        material: Arc<dyn Material>,
+    ) -> io::Result<Self> {
+        let mut curves = vec![];
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |message: &str| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: {}", number + 1, message),
+                )
+            };
+            let values = line
+                .split_whitespace()
+                .map(|value| value.parse::<f64>())
+                .collect::<Result<Vec<f64>, _>>()
+                .map_err(|error| invalid(&error.to_string()))?;
+            if values.len() < 14 || (values.len() - 2) % 9 != 3 {
+                return Err(invalid("expected 3n + 1 control points and two widths"));
+            }
+
+            let (points, widths) = values.split_at(values.len() - 2);
+            let points = points
+                .chunks(3)
+                .map(|p| Point3::new(p[0], p[1], p[2]))
+                .collect::<Vec<_>>();
+            let segments = (points.len() - 1) / 3;
+            let width = |u: f64| widths[0] + (widths[1] - widths[0]) * u;
+            for i in 0..segments {
+                let u = (i as f64 / segments as f64, (i + 1) as f64 / segments as f64);
+                let cp = [
+                    points[3 * i],
+                    points[3 * i + 1],
+                    points[3 * i + 2],
+                    points[3 * i + 3],
+                ];
+                curves.push(
+                    Curve::new(cp, (width(u.0), width(u.1)), material.clone()).with_shape(shape),
+                );
+            }
+        }
+        Ok(Self::new(curves))
+    }
+
+    /// Reads strands from the file at `path`, in the format of [read](CurveSet::read).
+    pub fn open(
+        path: impl AsRef<Path>,
+        shape: CurveShape,
+        material: Arc<dyn Material>,
+    ) -> io::Result<Self> {
+        Self::read(BufReader::new(File::open(path)?), shape, material)
+    }
+}
+
+impl Hittable for CurveSet {
+    /// Provides a definition of hit() for curve sets, the nearest hit among their curves.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.bvh.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bvh.bounding_box()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        material::Lambertian,
+        transform::{Transform, Transformed},
+    };
+
+    #[test]
+    fn curve_hit_test() {
+        let material = Arc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
+        let text = "# a straight strand along X, tapering\n\
+                    -1 0 0  -0.5 0 0  0 0 0  0.5 0 0  1 0 0  1.5 0 0  2 0 0  0.2 0.1\n";
+        let strands = CurveSet::read(text.as_bytes(), CurveShape::Cylinder, material).unwrap();
+
+        // Through the axis at a third of the way, where the width is 0.2 - 0.1 / 3.
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = strands.hit(&ray, 0.001, f64::MAX).unwrap();
+        assert!((rec.t - 5.0).abs() < 1e-9);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).norm() < 1e-6);
+        assert!(rec.dpdu.normalize().x > 0.999);
+        assert!((rec.width - (0.2 - 0.1 / 3.0)).abs() < 1e-9);
+
+        // Near the edge, the normal turns towards the side.
+        let edge = Ray::new(Point3::new(0.0, 0.08, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(strands.hit(&edge, 0.001, f64::MAX).unwrap().normal.y > 0.5);
+        let beside = Ray::new(Point3::new(0.0, 0.1, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(strands.hit(&beside, 0.001, f64::MAX).is_none());
+
+        // Hits right in front of the ray are kept, and a ray along the strand meets its root.
+        let close = Ray::new(Point3::new(0.0, 0.0, 0.05), Vec3::new(0.0, 0.0, -1.0));
+        let rec = strands.hit(&close, 0.001, f64::MAX).unwrap();
+        assert!((rec.t - 0.05).abs() < 1e-9);
+        let along = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let rec = strands.hit(&along, 0.001, f64::MAX).unwrap();
+        assert!((rec.t - 4.0).abs() < 1e-9);
+
+        // Scaling the strands scales their width.
+        let scaling = Transform::scaling(Vec3::new(2.0, 2.0, 2.0));
+        let scaled = Transformed::new(Arc::new(strands), scaling);
+        let rec = scaled.hit(&ray, 0.001, f64::MAX).unwrap();
+        assert!((rec.width - 2.0 * (0.2 - 0.1 / 3.0)).abs() < 1e-9);
+
+        assert!(CurveSet::read(
+            "0 0 0 1 1 1 0.1 0.1".as_bytes(),
+            CurveShape::Ribbon,
+            Arc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)))
+        )
+        .is_err());
+    }
+}
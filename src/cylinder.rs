@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    definitions::{solve_quadratic, PI},
+    frame::{azimuth, Frame},
+    hittable::nearest,
+    plane::Disk,
+    HitRecord, Hittable, Material, Point3, Ray, Vec3,
+};
+
+/// Defines a circular cylinder closed by a disk at each end.
+pub struct Cylinder {
+    frame: Frame,
+    radius: f64,
+    height: f64,
+    caps: [Disk; 2],
+    material: Arc<dyn Material>,
+}
+
+impl Cylinder {
+    /// Creates the cylinder of the given `radius` whose axis goes from `base` to `top`. Texture
+    /// coordinates on the side are the angle around the axis, `u`, and the height, `v`; on the caps
+    /// they are the same as on a [Disk].
+    pub fn new(base: Point3, top: Point3, radius: f64, material: Arc<dyn Material>) -> Self {
+        let axis = top - base;
+        Self {
+            frame: Frame::new(base, axis),
+            radius,
+            height: axis.norm(),
+            caps: [
+                Disk::new(base, -axis, radius, material.clone()),
+                Disk::new(top, axis, radius, material.clone()),
+            ],
+            material,
+        }
+    }
+
+    /// Returns the hit with the side of the cylinder at `t`, if it lies between the caps.
+    fn side_hit(&self, ray: &Ray, origin: &Point3, direction: &Vec3, t: f64) -> Option<HitRecord> {
+        let p = origin + t * direction;
+        if p.z < 0.0 || p.z > self.height {
+            return None;
+        }
+        let outward_normal = self.frame.from_local(&Vec3::new(p.x, p.y, 0.0)) / self.radius;
+        let dpdu = 2.0 * PI * Vec3::new(-p.y, p.x, 0.0);
+        let rec = HitRecord::new(ray, t, &outward_normal, self.material.clone());
+        Some(rec.with_uv(
+            (azimuth(&p), p.z / self.height),
+            (
+                self.frame.from_local(&dpdu),
+                self.height * self.frame.normal,
+            ),
+        ))
+    }
+}
+
+impl Hittable for Cylinder {
+    /// Provides a definition of hit() for cylinders.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (origin, d) = self.frame.ray_to_local(ray);
+        let side = solve_quadratic(
+            d.x * d.x + d.y * d.y,
+            2.0 * (origin.x * d.x + origin.y * d.y),
+            origin.x * origin.x + origin.y * origin.y - self.radius.powi(2),
+        )
+        .and_then(|(t0, t1)| {
+            [t0, t1]
+                .iter()
+                .filter(|t| (t_min..=t_max).contains(*t))
+                .find_map(|&t| self.side_hit(ray, &origin, &d, t))
+        });
+
+        self.caps.iter().fold(side, |best, cap| {
+            let t_max = best.as_ref().map_or(t_max, |rec| rec.t);
+            nearest(best, cap.hit(ray, t_min, t_max))
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let top = self.frame.origin + self.height * self.frame.normal;
+        let base = Aabb::around_circle(self.frame.origin, &self.frame.normal, self.radius);
+        Some(base.surrounding(&Aabb::around_circle(top, &self.frame.normal, self.radius)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+
+    #[test]
+    fn cylinder_hit_test() {
+        let material = Arc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
+        let cylinder = Cylinder::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, 2.0),
+            1.0,
+            material,
+        );
+
+        // Halfway up the side, opposite the start of the angle.
+        let ray = Ray::new(Point3::new(-5.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 0.0));
+        let rec = cylinder.hit(&ray, 0.001, f64::MAX).unwrap();
+        assert!((rec.t - 4.0).abs() < 1e-9);
+        assert!((rec.normal - Vec3::new(-1.0, 0.0, 0.0)).norm() < 1e-9);
+        assert!(rec.front_face);
+        assert!((rec.u - 0.5).abs() < 1e-9 && (rec.v - 0.5).abs() < 1e-9);
+
+        // The far side is seen from inside.
+        let rec = cylinder.hit(&ray, 4.1, f64::MAX).unwrap();
+        assert!((rec.t - 6.0).abs() < 1e-9);
+        assert!((rec.normal - Vec3::new(-1.0, 0.0, 0.0)).norm() < 1e-9);
+        assert!(!rec.front_face);
+        assert!(rec.u.abs() < 1e-9);
+
+        // The caps close both ends.
+        let down = Ray::new(Point3::new(0.0, 0.5, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = cylinder.hit(&down, 0.001, f64::MAX).unwrap();
+        assert!((rec.t - 3.0).abs() < 1e-9);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).norm() < 1e-9);
+        assert!(rec.front_face);
+        assert!((rec.u - 0.25).abs() < 1e-9 && (rec.v - 0.5).abs() < 1e-9);
+        let rec = cylinder.hit(&down, 3.1, f64::MAX).unwrap();
+        assert!((rec.t - 5.0).abs() < 1e-9);
+        assert!(!rec.front_face);
+
+        let above = Ray::new(Point3::new(-5.0, 0.0, 2.5), Vec3::new(1.0, 0.0, 0.0));
+        assert!(cylinder.hit(&above, 0.001, f64::MAX).is_none());
+        let beside = Ray::new(Point3::new(1.5, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(cylinder.hit(&beside, 0.001, f64::MAX).is_none());
+    }
+}
@@ -2,11 +2,15 @@ use crate::{
     hittable_list::HittableList,
     material::{Dielectric, Lambertian, Metal},
     ray::Point3,
+    sampler::Sampler,
     sphere::Sphere,
     Color, Vec3,
 };
-use rand::Rng;
-use std::sync::Arc;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{
+    cell::{Cell, RefCell},
+    sync::Arc,
+};
 
 /// Re-exports the definition of the constant PI.
 pub use std::f64::consts::PI;
@@ -19,9 +23,90 @@ pub fn degrees_to_radians(degrees: f64) -> f64 {
     degrees * PI / 180.0
 }
 
+thread_local! {
+    /// Generator behind every random helper, one per thread.
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+/// Reseeds the random number generator of the calling thread, so that everything generated on it
+/// afterwards (scenes, noise textures) is reproducible.
+pub fn seed_random(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
 /// Provides logic for generating random numbers of type f64 from 0.0 to 1.0 .
 pub fn random_double(min: f64, max: f64) -> f64 {
-    rand::thread_rng().gen_range(min..max)
+    RNG.with(|rng| rng.borrow_mut().gen_range(min..max))
+}
+
+/// Provides logic for generating random integers from `min` up to, but excluding, `max`.
+pub fn random_int(min: usize, max: usize) -> usize {
+    RNG.with(|rng| rng.borrow_mut().gen_range(min..max))
+}
+
+/// Defines the sample currently traced on a thread, the next dimensions being drawn from its
+/// sampler.
+struct SampleContext {
+    sampler: Arc<dyn Sampler>,
+    pixel: (usize, usize),
+    index: u32,
+    dimension: Cell<u32>,
+}
+
+thread_local! {
+    static SAMPLE: RefCell<Option<SampleContext>> = const { RefCell::new(None) };
+}
+
+/// Installs the sampler used by [sample_1d](sample_1d) and [sample_2d](sample_2d) on the calling
+/// thread, or removes it with None.
+pub fn set_sampler(sampler: Option<Arc<dyn Sampler>>) {
+    SAMPLE.with(|sample| {
+        *sample.borrow_mut() = sampler.map(|sampler| SampleContext {
+            sampler,
+            pixel: (0, 0),
+            index: 0,
+            dimension: Cell::new(0),
+        })
+    });
+}
+
+/// Starts sample `index` of pixel (`x`, `y`), drawing again from the first dimension.
+pub fn start_pixel_sample(x: usize, y: usize, index: u32) {
+    SAMPLE.with(|sample| {
+        if let Some(context) = sample.borrow_mut().as_mut() {
+            context.pixel = (x, y);
+            context.index = index;
+            context.dimension.set(0);
+        }
+    });
+}
+
+/// Provides logic for drawing the next dimension of the current sample, from 0.0 to 1.0 . Falls
+/// back to [random_double](random_double) when no sampler is installed.
+pub fn sample_1d() -> f64 {
+    SAMPLE.with(|sample| match sample.borrow().as_ref() {
+        Some(context) => {
+            let dimension = context.dimension.get();
+            context.dimension.set(dimension + 1);
+            let (x, y) = context.pixel;
+            context.sampler.get_1d(x, y, context.index, dimension)
+        }
+        None => random_double(0.0, 1.0),
+    })
+}
+
+/// Provides logic for drawing the next two dimensions of the current sample, see
+/// [sample_1d](sample_1d).
+pub fn sample_2d() -> (f64, f64) {
+    SAMPLE.with(|sample| match sample.borrow().as_ref() {
+        Some(context) => {
+            let dimension = context.dimension.get();
+            context.dimension.set(dimension + 2);
+            let (x, y) = context.pixel;
+            context.sampler.get_2d(x, y, context.index, dimension)
+        }
+        None => (random_double(0.0, 1.0), random_double(0.0, 1.0)),
+    })
 }
 
 /// Provides logic for generating a random Vec of type Vector3.
@@ -30,15 +115,26 @@ pub fn random_vec(min: f64, max: f64) -> Vec3 {
     Vec3::new(rd(), rd(), rd())
 }
 
-/// Provides logic for generating random Vectors inside a Unit sphere.
+/// Provides logic for generating random Vectors inside a Unit sphere, drawn from the current
+/// sample.
 pub fn random_in_unit_sphere() -> Vec3 {
-    loop {
-        let p = random_vec(-1.0, 1.0);
-        if p.dot(&p) >= 1.0 {
-            continue;
-        }
-        return p;
+    random_unit_vector() * sample_1d().cbrt()
+}
+
+/// Provides logic for generating random Vectors inside a Unit disk on the XY plane, drawn from the
+/// current sample with Shirley's concentric mapping.
+pub fn random_in_unit_disk() -> Vec3 {
+    let (u, v) = sample_2d();
+    let (a, b) = (2.0 * u - 1.0, 2.0 * v - 1.0);
+    if a == 0.0 && b == 0.0 {
+        return Vec3::new(0.0, 0.0, 0.0);
     }
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, PI / 4.0 * (b / a))
+    } else {
+        (b, PI / 2.0 - PI / 4.0 * (a / b))
+    };
+    Vec3::new(r * theta.cos(), r * theta.sin(), 0.0)
 }
 
 /// Provides logic for generating random Vectors inside a hemishpere.
@@ -52,9 +148,13 @@ pub fn random_in_hemisphere(normal: &Vec3) -> Vec3 {
     }
 }
 
-/// Provides logic for generating a random Unit Vector.
+/// Provides logic for generating a random Unit Vector, drawn from the current sample.
 pub fn random_unit_vector() -> Vec3 {
-    random_in_unit_sphere().normalize()
+    let (u, v) = sample_2d();
+    let z = 1.0 - 2.0 * u;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * v;
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
 }
 
 /// Provides logic for returning true if a Vector is very close to zero in all dimensions.
@@ -85,6 +185,93 @@ pub fn reflectance(cosine: f64, ref_idx: f64) -> f64 {
     r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
 }
 
+/// Returns the real roots of `a`t² + `b`t + `c`, in increasing order, a single root being
+/// returned twice.
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Option<(f64, f64)> {
+    if a.abs() < 1e-12 {
+        return (b.abs() > 1e-12).then(|| (-c / b, -c / b));
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    // Avoids cancelling out the two terms of the usual formula.
+    let q = -0.5 * (b + discriminant.sqrt().copysign(b));
+    let (t0, t1) = if q == 0.0 { (0.0, 0.0) } else { (q / a, c / q) };
+    Some((t0.min(t1), t0.max(t1)))
+}
+
+/// Returns the largest real root of t³ + `a`t² + `b`t + `c`.
+fn largest_cubic_root(a: f64, b: f64, c: f64) -> f64 {
+    // Depressed cubic s³ + ps + q, with t = s - a / 3.
+    let p = b - a * a / 3.0;
+    let q = 2.0 * a.powi(3) / 27.0 - a * b / 3.0 + c;
+    let discriminant = (q / 2.0).powi(2) + (p / 3.0).powi(3);
+    let s = if discriminant >= 0.0 {
+        let sqrtd = discriminant.sqrt();
+        (-q / 2.0 + sqrtd).cbrt() + (-q / 2.0 - sqrtd).cbrt()
+    } else {
+        let r = (-p / 3.0).sqrt();
+        let phi = (-q / (2.0 * r.powi(3))).clamp(-1.0, 1.0).acos();
+        2.0 * r * (phi / 3.0).cos()
+    };
+    s - a / 3.0
+}
+
+/// Returns the real roots of `a`t⁴ + `b`t³ + `c`t² + `d`t + `e`, in increasing order, following
+/// Ferrari's method and refined by Newton's method. Roots lost to overflow are left out.
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> Vec<f64> {
+    let (b, c, d, e) = (b / a, c / a, d / a, e / a);
+    // Depressed quartic y⁴ + py² + qy + r, with t = y - b / 4.
+    let p = c - 3.0 * b * b / 8.0;
+    let q = d - b * c / 2.0 + b.powi(3) / 8.0;
+    let r = e - b * d / 4.0 + b * b * c / 16.0 - 3.0 * b.powi(4) / 256.0;
+
+    let mut roots = vec![];
+    let mut add_squares = |z0: f64, z1: f64| {
+        for z in [z0, z1] {
+            if z >= 0.0 {
+                roots.extend_from_slice(&[-z.sqrt(), z.sqrt()]);
+            }
+        }
+    };
+    if q.abs() < 1e-12 {
+        // Biquadratic, a quadratic in y².
+        if let Some((z0, z1)) = solve_quadratic(1.0, p, r) {
+            add_squares(z0, z1);
+        }
+    } else {
+        // Any positive root m of the resolvent cubic splits the quartic into two quadratics.
+        let m = largest_cubic_root(p, p * p / 4.0 - r, -q * q / 8.0).max(1e-12);
+        let s = (2.0 * m).sqrt();
+        for sign in [1.0, -1.0] {
+            if let Some((y0, y1)) =
+                solve_quadratic(1.0, sign * s, p / 2.0 + m - sign * q / (2.0 * s))
+            {
+                roots.extend_from_slice(&[y0, y1]);
+            }
+        }
+    }
+
+    let mut roots = roots
+        .into_iter()
+        .map(|y| {
+            let mut t = y - b / 4.0;
+            for _ in 0..2 {
+                let f = (((t + b) * t + c) * t + d) * t + e;
+                let df = ((4.0 * t + 3.0 * b) * t + 2.0 * c) * t + d;
+                if df.abs() > 1e-12 {
+                    t -= f / df;
+                }
+            }
+            t
+        })
+        .filter(|t| t.is_finite())
+        .collect::<Vec<f64>>();
+    roots.sort_by(f64::total_cmp);
+    roots
+}
+
 pub fn random_scene() -> HittableList {
     let mut world = HittableList::default();
 
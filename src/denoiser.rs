@@ -0,0 +1,230 @@
+use rayon::prelude::*;
+
+use crate::{aov::AovBuffer, color::luminance, framebuffer::FrameBuffer, Color};
+
+/// Defines a joint bilateral filter removing the noise of low sample count renders. It averages
+/// each pixel with its neighbours, giving less weight to those with a different albedo or normal,
+/// as given by the auxiliary buffers, or whose luminance differs by more than their noise explains.
+/// Filtering is done on the image divided by its albedo, so textures stay sharp.
+#[derive(Debug, Clone, Copy)]
+pub struct Denoiser {
+    radius: usize,
+    sigma_spatial: f64,
+    sigma_luminance: f64,
+    sigma_albedo: f64,
+    sigma_normal: f64,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self {
+            radius: 6,
+            sigma_spatial: 3.0,
+            sigma_luminance: 2.0,
+            sigma_albedo: 0.1,
+            sigma_normal: 0.1,
+        }
+    }
+}
+
+impl Denoiser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how many pixels away neighbours are looked for.
+    pub fn radius(mut self, radius: usize) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    /// Sets the standard deviation, in pixels, of the falloff with distance.
+    pub fn sigma_spatial(mut self, sigma_spatial: f64) -> Self {
+        self.sigma_spatial = sigma_spatial;
+        self
+    }
+
+    /// Sets how many standard errors of noise two luminances may differ by and still be blended.
+    pub fn sigma_luminance(mut self, sigma_luminance: f64) -> Self {
+        self.sigma_luminance = sigma_luminance;
+        self
+    }
+
+    /// Sets how far apart two albedos may be and still be blended.
+    pub fn sigma_albedo(mut self, sigma_albedo: f64) -> Self {
+        self.sigma_albedo = sigma_albedo;
+        self
+    }
+
+    /// Sets how far apart, as one minus the cosine of their angle, two normals may be and still be
+    /// blended.
+    pub fn sigma_normal(mut self, sigma_normal: f64) -> Self {
+        self.sigma_normal = sigma_normal;
+        self
+    }
+
+    /// Returns the denoised image, `aovs` having been rendered along with `image`.
+    pub fn denoise(&self, image: &FrameBuffer, aovs: &AovBuffer) -> FrameBuffer {
+        let (width, height) = (image.width(), image.height());
+        let rows = (0..height)
+            .into_par_iter()
+            .map(|y| {
+                (0..width)
+                    .map(|x| self.filter_pixel(image, aovs, x, y))
+                    .collect::<Vec<Color>>()
+            })
+            .collect::<Vec<Vec<Color>>>();
+
+        let mut denoised = FrameBuffer::new(width, height);
+        for (y, row) in rows.into_iter().enumerate() {
+            for (x, color) in row.into_iter().enumerate() {
+                denoised.add_sample(x, y, color);
+            }
+        }
+        denoised
+    }
+
+    /// Returns the weighted average of the neighbourhood of pixel (`x`, `y`).
+    fn filter_pixel(&self, image: &FrameBuffer, aovs: &AovBuffer, x: usize, y: usize) -> Color {
+        let (albedo, normal) = (aovs.albedo(x, y), aovs.normal(x, y));
+        let (luminance_centre, variance_centre) =
+            (luminance(&image.pixel(x, y)), image.mean_variance(x, y));
+
+        let (mut sum, mut total_weight) = (Color::new(0.0, 0.0, 0.0), 0.0);
+        let rows = y.saturating_sub(self.radius)..(y + self.radius + 1).min(image.height());
+        let columns = x.saturating_sub(self.radius)..(x + self.radius + 1).min(image.width());
+        for qy in rows {
+            for qx in columns.clone() {
+                let (color, albedo_q) = (image.pixel(qx, qy), aovs.albedo(qx, qy));
+
+                let distance = (qx as f64 - x as f64).powi(2) + (qy as f64 - y as f64).powi(2);
+                let spatial_term = distance / (2.0 * self.sigma_spatial.powi(2));
+                let deviation = (variance_centre + image.mean_variance(qx, qy)).sqrt();
+                let luminance_term = (luminance(&color) - luminance_centre).abs()
+                    / (self.sigma_luminance * deviation + 1e-6);
+                let albedo_term = (albedo_q - albedo).norm_squared() / self.sigma_albedo.powi(2);
+                let normal_term =
+                    (1.0 - aovs.normal(qx, qy).dot(&normal)).max(0.0) / self.sigma_normal;
+
+                let weight = (-spatial_term - luminance_term - albedo_term - normal_term).exp();
+                // Irradiance is smoother than radiance, divide out the albedo and put it back
+                // afterwards.
+                sum += weight * demodulate(color, albedo_q);
+                total_weight += weight;
+            }
+        }
+        (sum / total_weight).zip_map(&albedo, |c, a| if a > ALBEDO_EPSILON { c * a } else { c })
+    }
+}
+
+/// Albedo below which a channel is left as it is rather than divided out.
+const ALBEDO_EPSILON: f64 = 1e-3;
+
+/// Returns `color` divided by `albedo`, channel by channel.
+fn demodulate(color: Color, albedo: Color) -> Color {
+    color.zip_map(&albedo, |c, a| if a > ALBEDO_EPSILON { c / a } else { c })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        definitions::{random_double, seed_random},
+        HitRecord, Lambertian, Point3, Ray, Vec3,
+    };
+    use std::sync::Arc;
+
+    const SIZE: usize = 12;
+
+    /// Returns an image of `samples` samples per pixel given by `radiance`, along with the
+    /// auxiliary buffers of a surface of the albedo and normal given by `surface`.
+    fn render(
+        samples: usize,
+        radiance: impl Fn(usize, usize, usize) -> Color,
+        surface: impl Fn(usize, usize) -> (Color, Vec3),
+    ) -> (FrameBuffer, AovBuffer) {
+        let (mut image, mut aovs) = (FrameBuffer::new(SIZE, SIZE), AovBuffer::new(SIZE, SIZE));
+        let ray = Ray::new(Point3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        for y in 0..SIZE {
+            for x in 0..SIZE {
+                let (albedo, normal) = surface(x, y);
+                let rec = HitRecord::new(&ray, 1.0, &normal, Arc::new(Lambertian::new(albedo)));
+                for k in 0..samples {
+                    image.add_sample(x, y, radiance(x, y, k));
+                    aovs.add_sample(x, y, &ray, Some(&rec), None);
+                }
+            }
+        }
+        (image, aovs)
+    }
+
+    /// Returns the mean squared difference between the pixels of `image` and `expected`.
+    fn error(image: &FrameBuffer, expected: impl Fn(usize, usize) -> Color) -> f64 {
+        let pixels = (0..SIZE).flat_map(|y| (0..SIZE).map(move |x| (x, y)));
+        pixels
+            .map(|(x, y)| (image.pixel(x, y) - expected(x, y)).norm_squared())
+            .sum::<f64>()
+            / (SIZE * SIZE) as f64
+    }
+
+    #[test]
+    fn flat_test() {
+        seed_random(7);
+        let grey = Color::new(0.5, 0.5, 0.5);
+        let noisy = |_, _, _| grey * random_double(0.0, 2.0);
+        let flat = |_, _| (Color::new(0.8, 0.8, 0.8), Vec3::new(0.0, 0.0, 1.0));
+        let (image, aovs) = render(4, noisy, flat);
+        let denoised = Denoiser::new().denoise(&image, &aovs);
+
+        assert!(error(&denoised, |_, _| grey) < error(&image, |_, _| grey) / 4.0);
+    }
+
+    #[test]
+    fn edge_test() {
+        // Alternating samples give each pixel the same mean but a noisy enough variance that the
+        // luminance alone would not keep the two sides apart.
+        let white = Color::new(1.0, 1.0, 1.0);
+        let expected = |x, _| white * if x < SIZE / 2 { 0.8 } else { 0.2 };
+        let radiance = |x, y, k| expected(x, y) + white * if k % 2 == 0 { 0.3 } else { -0.3 };
+
+        // A crease, where the normal turns, or a change of paint, where the albedo does, stays
+        // sharp.
+        let crease = |x, _| {
+            let normal = if x < SIZE / 2 {
+                Vec3::new(0.0, 0.0, 1.0)
+            } else {
+                Vec3::new(0.0, 1.0, 0.0)
+            };
+            (white, normal)
+        };
+        let (image, aovs) = render(4, radiance, crease);
+        assert!(error(&image, expected) < 1e-12);
+        assert!(error(&Denoiser::new().denoise(&image, &aovs), expected) < 1e-4);
+
+        let paint = |x, y| (expected(x, y), Vec3::new(0.0, 0.0, 1.0));
+        let (image, aovs) = render(4, radiance, paint);
+        assert!(error(&Denoiser::new().denoise(&image, &aovs), expected) < 1e-4);
+
+        // On a plain surface the edge is blurred.
+        let plain = |_, _| (white, Vec3::new(0.0, 0.0, 1.0));
+        let (image, aovs) = render(4, radiance, plain);
+        assert!(error(&Denoiser::new().denoise(&image, &aovs), expected) > 1e-3);
+    }
+
+    #[test]
+    fn single_sample_test() {
+        seed_random(7);
+        let noisy = |_, _, _| Color::new(random_double(0.0, 4.0), 0.5, 0.0);
+        let flat = |_, _| (Color::new(0.8, 0.8, 0.8), Vec3::new(0.0, 0.0, 1.0));
+        let (image, aovs) = render(1, noisy, flat);
+        let denoised = Denoiser::new().denoise(&image, &aovs);
+
+        for y in 0..SIZE {
+            for x in 0..SIZE {
+                let color = denoised.pixel(x, y);
+                assert!(color.iter().all(|c| c.is_finite()));
+                assert!((0.0..4.0).contains(&color.x));
+            }
+        }
+    }
+}
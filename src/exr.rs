@@ -0,0 +1,269 @@
+use std::io::{self, Write};
+
+use crate::{aov::AovBuffer, framebuffer::FrameBuffer, Vec3};
+
+/// Defines how the samples of every channel are stored in the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleFormat {
+    /// 16-bit floats, half the size and precise enough for colours.
+    Half,
+    /// 32-bit floats, needed for depth and positions.
+    Float,
+}
+
+/// Defines an OpenEXR image made of named float channels, grouped in layers by a common prefix as
+/// in `albedo.R`. It is written as a single part, uncompressed scanline file.
+#[derive(Debug, Clone)]
+pub struct ExrImage {
+    width: usize,
+    height: usize,
+    format: SampleFormat,
+    /// Name and samples of each channel, starting from the top row.
+    channels: Vec<(String, Vec<f32>)>,
+}
+
+impl ExrImage {
+    /// Creates an image of the given size without any channels.
+    pub fn new(width: usize, height: usize, format: SampleFormat) -> Self {
+        Self {
+            width,
+            height,
+            format,
+            channels: vec![],
+        }
+    }
+
+    /// Creates an image holding the linear colours of `image` in its `R`, `G` and `B` channels.
+    pub fn from_framebuffer(image: &FrameBuffer, format: SampleFormat) -> Self {
+        Self::new(image.width(), image.height(), format).with_layer("", image)
+    }
+
+    /// Used to add one channel, `value` giving its sample at every pixel (`x`, `y`).
+    pub fn with_channel(mut self, name: &str, value: impl Fn(usize, usize) -> f64) -> Self {
+        let samples = (0..self.height)
+            .rev()
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|(x, y)| value(x, y) as f32)
+            .collect();
+        self.channels.retain(|(channel, _)| channel != name);
+        self.channels.push((name.to_string(), samples));
+        self
+    }
+
+    /// Used to add a layer of three channels, suffixed by `components`, from a vector per pixel.
+    fn with_vectors(
+        mut self,
+        layer: &str,
+        components: [&str; 3],
+        value: impl Fn(usize, usize) -> Vec3,
+    ) -> Self {
+        for (i, component) in components.iter().enumerate() {
+            let name = match layer {
+                "" => component.to_string(),
+                _ => format!("{}.{}", layer, component),
+            };
+            self = self.with_channel(&name, |x, y| value(x, y)[i]);
+        }
+        self
+    }
+
+    /// Used to add the colours of `image` as the `R`, `G` and `B` channels of `layer`, or of the
+    /// default layer if the name is empty.
+    pub fn with_layer(self, layer: &str, image: &FrameBuffer) -> Self {
+        self.with_vectors(layer, ["R", "G", "B"], |x, y| image.pixel(x, y))
+    }
+
+    /// Used to add the auxiliary buffers as the `albedo`, `N` (normal), `P` (position), `Z`
+    /// (depth) and `objectId` layers. Pixels where nothing is hit get zero, except for depth which
+    /// gets infinity.
+    pub fn with_aovs(self, aovs: &AovBuffer) -> Self {
+        let zero = Vec3::new(0.0, 0.0, 0.0);
+        self.with_vectors("albedo", ["R", "G", "B"], |x, y| aovs.albedo(x, y))
+            .with_vectors("N", ["X", "Y", "Z"], |x, y| aovs.normal(x, y))
+            .with_vectors("P", ["X", "Y", "Z"], |x, y| {
+                aovs.position(x, y).unwrap_or(zero)
+            })
+            .with_channel("Z", |x, y| aovs.depth(x, y).unwrap_or(f64::INFINITY))
+            .with_channel("objectId", |x, y| {
+                aovs.object_id(x, y).map_or(0.0, |id| id as f64 + 1.0)
+            })
+    }
+
+    /// Writes the image as an OpenEXR file.
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        // Readers expect the channels sorted by name, both in the header and in the pixel data.
+        let mut channels = self.channels.iter().collect::<Vec<_>>();
+        channels.sort_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+        let (pixel_type, sample_size) = match self.format {
+            SampleFormat::Half => (1i32, 2),
+            SampleFormat::Float => (2i32, 4),
+        };
+
+        let mut header = vec![];
+        // Magic number, then version 2 with the long names flag.
+        header.extend_from_slice(&[0x76, 0x2f, 0x31, 0x01, 2, 4, 0, 0]);
+        let mut channel_list = vec![];
+        for (name, _) in &channels {
+            channel_list.extend_from_slice(name.as_bytes());
+            channel_list.push(0);
+            channel_list.extend_from_slice(&pixel_type.to_le_bytes());
+            // Perceptually linear flag and reserved bytes, then the x and y subsampling.
+            channel_list.extend_from_slice(&[0; 4]);
+            channel_list.extend_from_slice(&1i32.to_le_bytes());
+            channel_list.extend_from_slice(&1i32.to_le_bytes());
+        }
+        channel_list.push(0);
+        let window = [0, 0, self.width as i32 - 1, self.height as i32 - 1]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect::<Vec<u8>>();
+        attribute(&mut header, "channels", "chlist", &channel_list);
+        attribute(&mut header, "compression", "compression", &[0]);
+        attribute(&mut header, "dataWindow", "box2i", &window);
+        attribute(&mut header, "displayWindow", "box2i", &window);
+        attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+        attribute(
+            &mut header,
+            "pixelAspectRatio",
+            "float",
+            &1f32.to_le_bytes(),
+        );
+        attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+        attribute(
+            &mut header,
+            "screenWindowWidth",
+            "float",
+            &1f32.to_le_bytes(),
+        );
+        header.push(0);
+        out.write_all(&header)?;
+
+        // Offsets of every scanline, which follow this table and are each prefixed by their row
+        // and size.
+        let line_size = channels.len() * self.width * sample_size;
+        let first_line = (header.len() + 8 * self.height) as u64;
+        for y in 0..self.height as u64 {
+            out.write_all(&(first_line + y * (8 + line_size as u64)).to_le_bytes())?;
+        }
+
+        let mut line = Vec::with_capacity(line_size);
+        for y in 0..self.height {
+            line.clear();
+            for (_, samples) in &channels {
+                for &sample in &samples[y * self.width..(y + 1) * self.width] {
+                    match self.format {
+                        SampleFormat::Half => {
+                            line.extend_from_slice(&to_half(sample).to_le_bytes())
+                        }
+                        SampleFormat::Float => line.extend_from_slice(&sample.to_le_bytes()),
+                    }
+                }
+            }
+            out.write_all(&(y as i32).to_le_bytes())?;
+            out.write_all(&(line_size as i32).to_le_bytes())?;
+            out.write_all(&line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Appends a header attribute: its name, type name, size and value.
+fn attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    for text in [name, kind] {
+        header.extend_from_slice(text.as_bytes());
+        header.push(0);
+    }
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+/// Returns the bits of the 16-bit float nearest to `value`, ties to even. Values too large become
+/// infinite and values too small become subnormal or zero.
+fn to_half(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    if exponent == 0xff {
+        // Infinity, or a NaN kept quiet.
+        let nan = if mantissa != 0 { 0x200 } else { 0 };
+        return sign | 0x7c00 | nan;
+    }
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    let (mantissa, shift) = if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+        // Subnormal, with the implicit leading bit made explicit.
+        (mantissa | 0x80_0000, (14 - exponent) as u32)
+    } else {
+        (mantissa, 13)
+    };
+    let half = mantissa >> shift;
+    let rest = mantissa & ((1 << shift) - 1);
+    let halfway = 1 << (shift - 1);
+    let rounded = if rest > halfway || (rest == halfway && half & 1 == 1) {
+        half + 1
+    } else {
+        half
+    };
+    // Rounding may carry into the exponent, which the addition handles on its own.
+    let exponent = exponent.max(0) as u32;
+    sign | ((exponent << 10) + rounded) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use super::*;
+
+    #[test]
+    fn half_test() {
+        assert_eq!(to_half(0.0), 0x0000);
+        assert_eq!(to_half(-0.0), 0x8000);
+        assert_eq!(to_half(1.0), 0x3c00);
+        assert_eq!(to_half(-2.0), 0xc000);
+        assert_eq!(to_half(0.1), 0x2e66);
+        assert_eq!(to_half(65504.0), 0x7bff);
+        assert_eq!(to_half(1e6), 0x7c00);
+        assert_eq!(to_half(f32::INFINITY), 0x7c00);
+        assert_eq!(to_half(5.960_464_5e-8), 0x0001);
+        assert_eq!(to_half(6.097_555e-5), 0x03ff);
+        assert_eq!(to_half(1.0 + 1.0 / 2048.0), 0x3c00);
+        assert_eq!(to_half(2047.5), 0x67ff + 1);
+    }
+
+    #[test]
+    fn layout_test() {
+        let mut image = FrameBuffer::new(3, 2);
+        image.add_sample(0, 1, Vec3::new(1.0, 2.0, 3.0));
+        let mut bytes = vec![];
+        ExrImage::from_framebuffer(&image, SampleFormat::Float)
+            .with_channel("A", |_, _| 0.5)
+            .write(&mut bytes)
+            .unwrap();
+
+        assert_eq!(bytes[..4], [0x76, 0x2f, 0x31, 0x01]);
+        // Each scanline holds its row and size, then the channels A, B, G and R of three pixels.
+        let line_size = 8 + 4 * 3 * 4;
+        let offset_table = bytes.len() - 2 * line_size - 2 * 8;
+        let first_line = u64::from_le_bytes(bytes[offset_table..][..8].try_into().unwrap());
+        assert_eq!(first_line as usize, offset_table + 16);
+
+        let sample = |channel: usize, x: usize| {
+            let start = first_line as usize + 8 + (channel * 3 + x) * 4;
+            f32::from_le_bytes(bytes[start..start + 4].try_into().unwrap())
+        };
+        // The top row comes first: A, then B, G and R of the pixel at (0, 1).
+        assert_eq!(sample(0, 0), 0.5);
+        assert_eq!(sample(1, 0), 3.0);
+        assert_eq!(sample(2, 0), 2.0);
+        assert_eq!(sample(3, 0), 1.0);
+        assert_eq!(sample(3, 1), 0.0);
+    }
+}
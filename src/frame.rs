@@ -0,0 +1,61 @@
+use crate::{definitions::PI, Point3, Ray, Vec3};
+
+/// Defines a local coordinate system with its origin at `origin` and its Z axis along `normal`,
+/// used to intersect shapes in the position they are modelled in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frame {
+    pub origin: Point3,
+    pub tangent: Vec3,
+    pub bitangent: Vec3,
+    pub normal: Vec3,
+}
+
+impl Frame {
+    /// Creates a right-handed frame around `axis`, which does not need to be normalised, following
+    /// Duff et al. "Building an Orthonormal Basis, Revisited".
+    pub fn new(origin: Point3, axis: Vec3) -> Self {
+        let n = axis.normalize();
+        let sign = 1f64.copysign(n.z);
+        let a = -1.0 / (sign + n.z);
+        let b = n.x * n.y * a;
+        Self {
+            origin,
+            tangent: Vec3::new(1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x),
+            bitangent: Vec3::new(b, sign + n.y * n.y * a, -n.y),
+            normal: n,
+        }
+    }
+
+    /// Returns the local coordinates of a world direction.
+    pub fn to_local(&self, v: &Vec3) -> Vec3 {
+        Vec3::new(
+            v.dot(&self.tangent),
+            v.dot(&self.bitangent),
+            v.dot(&self.normal),
+        )
+    }
+
+    /// Returns the world direction of local coordinates.
+    pub fn from_local(&self, v: &Vec3) -> Vec3 {
+        v.x * self.tangent + v.y * self.bitangent + v.z * self.normal
+    }
+
+    /// Returns the origin and direction of a world ray in local coordinates, the direction keeping
+    /// its length so that distances along both rays match.
+    pub fn ray_to_local(&self, ray: &Ray) -> (Point3, Vec3) {
+        (
+            self.to_local(&(ray.origin() - self.origin)),
+            self.to_local(&ray.direction()),
+        )
+    }
+}
+
+/// Returns the angle of a local point around the Z axis, in [0, 1) turns.
+pub fn azimuth(p: &Point3) -> f64 {
+    let phi = p.y.atan2(p.x);
+    if phi < 0.0 {
+        (phi + 2.0 * PI) / (2.0 * PI)
+    } else {
+        phi / (2.0 * PI)
+    }
+}
@@ -0,0 +1,390 @@
+use std::io::{self, Write};
+
+use crate::{
+    color::{anti_aliased, luminance},
+    definitions::INFINITY,
+    pixel_filter::PixelFilter,
+    spectrum::wavelength_to_rgb,
+    Color,
+};
+
+/// Defines a floating point image that accumulates weighted radiance samples per pixel. It also
+/// keeps the running mean and variance of the luminance of the samples taken in each pixel, to
+/// estimate how noisy it still is.
+#[derive(Clone)]
+pub struct FrameBuffer {
+    width: usize,
+    height: usize,
+    sum: Vec<Color>,
+    weight: Vec<f64>,
+    count: Vec<u32>,
+    mean: Vec<f64>,
+    m2: Vec<f64>,
+}
+
+impl FrameBuffer {
+    /// Creates a black image of the given size, without any samples.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            sum: vec![Color::new(0.0, 0.0, 0.0); width * height],
+            weight: vec![0.0; width * height],
+            count: vec![0; width * height],
+            mean: vec![0.0; width * height],
+            m2: vec![0.0; width * height],
+        }
+    }
+
+    /// Returns the width of the image in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the image in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        y * self.width + x
+    }
+
+    /// Updates the statistics of the pixel at (`x`, `y`) with a sample taken inside it, following
+    /// Welford's algorithm.
+    fn record(&mut self, x: usize, y: usize, color: &Color) {
+        let i = self.index(x, y);
+        let value = luminance(color);
+        self.count[i] += 1;
+        let delta = value - self.mean[i];
+        self.mean[i] += delta / self.count[i] as f64;
+        self.m2[i] += delta * (value - self.mean[i]);
+    }
+
+    /// Adds an RGB radiance sample to the pixel at (`x`, `y`), `y` counting up from the bottom row.
+    pub fn add_sample(&mut self, x: usize, y: usize, color: Color) {
+        self.record(x, y, &color);
+        self.add_weighted_sample(x, y, color, 1.0);
+    }
+
+    /// Adds an RGB radiance sample to the pixel at (`x`, `y`), counting for `weight` samples. The
+    /// sample is not taken into account by the noise estimate.
+    pub fn add_weighted_sample(&mut self, x: usize, y: usize, color: Color, weight: f64) {
+        let i = self.index(x, y);
+        self.sum[i] += weight * color;
+        self.weight[i] += weight;
+    }
+
+    /// Spreads a sample taken at the continuous image position (`x`, `y`), pixel (i, j) covering
+    /// [i, i + 1) × [j, j + 1), over every pixel within the reach of `filter`.
+    pub fn splat(&mut self, x: f64, y: f64, color: Color, filter: &PixelFilter) {
+        let (px, py) = (x.floor(), y.floor());
+        if px >= 0.0 && py >= 0.0 && (px as usize) < self.width && (py as usize) < self.height {
+            self.record(px as usize, py as usize, &color);
+        }
+
+        let radius = filter.radius();
+        let range = |centre: f64, size: usize| {
+            let low = (centre - radius - 0.5).ceil().max(0.0) as usize;
+            let high = ((centre + radius - 0.5).floor() + 1.0).clamp(0.0, size as f64) as usize;
+            low..high
+        };
+        for j in range(y, self.height) {
+            for i in range(x, self.width) {
+                let weight = filter.evaluate(x - (i as f64 + 0.5), y - (j as f64 + 0.5));
+                if weight != 0.0 {
+                    self.add_weighted_sample(i, j, color, weight);
+                }
+            }
+        }
+    }
+
+    /// Spreads a sample traced at a single wavelength, see [splat](FrameBuffer::splat).
+    pub fn splat_spectral(
+        &mut self,
+        x: f64,
+        y: f64,
+        color: Color,
+        lambda: f64,
+        filter: &PixelFilter,
+    ) {
+        self.splat(
+            x,
+            y,
+            color.component_mul(&wavelength_to_rgb(lambda)),
+            filter,
+        );
+    }
+
+    /// Adds a radiance sample traced at a single wavelength, converting it back to RGB through the
+    /// CIE colour matching functions.
+    pub fn add_spectral_sample(&mut self, x: usize, y: usize, color: Color, lambda: f64) {
+        self.add_sample(x, y, color.component_mul(&wavelength_to_rgb(lambda)));
+    }
+
+    /// Sums the samples of two images of the same size.
+    pub fn merge(mut self, other: FrameBuffer) -> Self {
+        for i in 0..self.sum.len() {
+            self.sum[i] += other.sum[i];
+            self.weight[i] += other.weight[i];
+
+            // Combines the statistics of both sets of samples, following Chan et al.
+            let (na, nb) = (self.count[i] as f64, other.count[i] as f64);
+            if nb == 0.0 {
+                continue;
+            }
+            let n = na + nb;
+            let delta = other.mean[i] - self.mean[i];
+            self.mean[i] += delta * nb / n;
+            self.m2[i] += other.m2[i] + delta * delta * na * nb / n;
+            self.count[i] += other.count[i];
+        }
+        self
+    }
+
+    /// Returns the number of samples taken inside the pixel at (`x`, `y`).
+    pub fn sample_count(&self, x: usize, y: usize) -> u32 {
+        self.count[self.index(x, y)]
+    }
+
+    /// Returns the estimated variance of the mean luminance of the pixel at (`x`, `y`), infinite
+    /// until it holds two samples.
+    pub fn mean_variance(&self, x: usize, y: usize) -> f64 {
+        let i = self.index(x, y);
+        if self.count[i] < 2 {
+            return INFINITY;
+        }
+        let n = self.count[i] as f64;
+        self.m2[i] / (n - 1.0) / n
+    }
+
+    /// Returns the estimated standard error of the luminance of the pixel at (`x`, `y`), relative
+    /// to its brightness. The offset keeps nearly black pixels from asking for endless samples.
+    pub fn relative_error(&self, x: usize, y: usize) -> f64 {
+        let mean = self.mean[self.index(x, y)];
+        self.mean_variance(x, y).sqrt() / (mean.abs() + 0.01)
+    }
+
+    /// Returns an image of the number of samples taken in each pixel, going from blue for the
+    /// fewest to green and then red for the most.
+    pub fn sample_heatmap(&self) -> FrameBuffer {
+        let (min, max) = (
+            self.count.iter().copied().min().unwrap_or(0),
+            self.count.iter().copied().max().unwrap_or(0),
+        );
+        let mut heatmap = FrameBuffer::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let t = if max > min {
+                    (self.sample_count(x, y) - min) as f64 / (max - min) as f64
+                } else {
+                    0.0
+                };
+                let color = if t < 0.5 {
+                    Color::new(0.0, 2.0 * t, 1.0 - 2.0 * t)
+                } else {
+                    Color::new(2.0 * t - 1.0, 2.0 - 2.0 * t, 0.0)
+                };
+                heatmap.add_sample(x, y, color);
+            }
+        }
+        heatmap
+    }
+
+    /// Returns the linear radiance estimate of the pixel at (`x`, `y`).
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        let i = self.index(x, y);
+        // Filters with negative lobes can leave a pixel without any meaningful weight.
+        if self.weight[i] > 1e-8 {
+            self.sum[i] / self.weight[i]
+        } else {
+            Color::new(0.0, 0.0, 0.0)
+        }
+    }
+
+    /// Returns the gamma corrected 8-bit RGB values of the image, starting from the top row.
+    pub fn to_rgb8(&self) -> Vec<u8> {
+        (0..self.height)
+            .rev()
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .flat_map(|(x, y)| {
+                anti_aliased(self.pixel(x, y), 1)
+                    .iter()
+                    .map(|c| (c * 255.9) as u8)
+                    .collect::<Vec<u8>>()
+            })
+            .collect()
+    }
+
+    /// Writes the image as a plain text PPM.
+    pub fn write_ppm(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "P3\n{} {}\n255", self.width, self.height)?;
+        for pc in self.to_rgb8().chunks(3) {
+            writeln!(out, "{} {} {}", pc[0], pc[1], pc[2])?;
+        }
+        Ok(())
+    }
+
+    /// Writes the linear image as a Radiance HDR file, using uncompressed RGBE scanlines. Negative
+    /// values cannot be stored and are clamped to zero.
+    pub fn write_hdr(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(
+            out,
+            "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+            self.height, self.width
+        )?;
+        for y in (0..self.height).rev() {
+            for x in 0..self.width {
+                out.write_all(&to_rgbe(self.pixel(x, y)))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the shared exponent encoding of a colour: three 8-bit mantissas and one exponent.
+fn to_rgbe(color: Color) -> [u8; 4] {
+    let color = color.map(|c| c.max(0.0));
+    let max = color.max();
+    if max < 1e-32 {
+        return [0; 4];
+    }
+    // Exponent such that max / 2^exponent lies in [0.5, 1).
+    let exponent = max.log2().floor() as i32 + 1;
+    let scale = 256.0 / 2f64.powi(exponent);
+    let mantissa = |c: f64| (c * scale).min(255.0) as u8;
+    [
+        mantissa(color.x),
+        mantissa(color.y),
+        mantissa(color.z),
+        (exponent + 128).clamp(0, 255) as u8,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splat_test() {
+        let (red, blue) = (Color::new(1.0, 0.0, 0.0), Color::new(0.0, 0.0, 1.0));
+
+        // A box of radius 0.5 keeps every sample in its own pixel.
+        let mut image = FrameBuffer::new(3, 1);
+        image.splat(1.25, 0.5, red, &PixelFilter::default());
+        assert_eq!(image.pixel(0, 0), Color::new(0.0, 0.0, 0.0));
+        assert_eq!(image.pixel(1, 0), red);
+
+        // A tent of radius 1 shares each sample with the neighbour it is closest to.
+        let tent = PixelFilter::Tent { radius: 1.0 };
+        let mut image = FrameBuffer::new(3, 1);
+        image.splat(1.25, 0.5, red, &tent);
+        image.splat(1.75, 0.5, blue, &tent);
+        assert!((image.pixel(0, 0) - red).norm() < 1e-12);
+        assert!((image.pixel(1, 0) - Color::new(0.5, 0.0, 0.5)).norm() < 1e-12);
+        assert!((image.pixel(2, 0) - blue).norm() < 1e-12);
+        assert_eq!((image.sample_count(0, 0), image.sample_count(1, 0)), (0, 2));
+
+        // Samples just outside the image still reach the pixels along its edge.
+        let mut image = FrameBuffer::new(3, 1);
+        image.splat(-0.25, 0.5, blue, &tent);
+        assert!((image.pixel(0, 0) - blue).norm() < 1e-12);
+        assert_eq!(image.sample_count(0, 0), 0);
+        assert_eq!(image.pixel(1, 0), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn statistics_test() {
+        let grey = |v: f64| Color::new(v, v, v);
+        let mut image = FrameBuffer::new(3, 1);
+        image.add_sample(0, 0, grey(1.0));
+        // One sample says nothing of the noise, the pixel is far from converged.
+        assert!(image.relative_error(0, 0) > 1e100);
+
+        // Luminances 1, 2, 3 and 4: a mean of 2.5 and a sample variance of 5/3.
+        let mut other = FrameBuffer::new(3, 1);
+        for v in [2.0, 3.0, 4.0] {
+            other.add_sample(0, 0, grey(v));
+        }
+        let expected = (5.0 / 3.0 / 4.0f64).sqrt() / 2.51;
+        let image = image.merge(other);
+        assert_eq!(image.sample_count(0, 0), 4);
+        assert!((image.relative_error(0, 0) - expected).abs() < 1e-12);
+
+        // The same samples taken one after the other give the same estimate.
+        let mut single = FrameBuffer::new(3, 1);
+        for v in [1.0, 2.0, 3.0, 4.0] {
+            single.add_sample(0, 0, grey(v));
+        }
+        assert!((single.relative_error(0, 0) - expected).abs() < 1e-12);
+
+        // A constant pixel has no error left.
+        let mut constant = FrameBuffer::new(1, 1);
+        constant.add_sample(0, 0, grey(0.5));
+        constant.add_sample(0, 0, grey(0.5));
+        assert_eq!(constant.relative_error(0, 0), 0.0);
+    }
+
+    #[test]
+    fn sample_heatmap_test() {
+        let mut image = FrameBuffer::new(3, 1);
+        for (x, count) in [(0, 1), (1, 3), (2, 5)] {
+            for _ in 0..count {
+                image.add_sample(x, 0, Color::new(0.5, 0.5, 0.5));
+            }
+        }
+        let heatmap = image.sample_heatmap();
+        assert_eq!(heatmap.pixel(0, 0), Color::new(0.0, 0.0, 1.0));
+        assert_eq!(heatmap.pixel(1, 0), Color::new(0.0, 1.0, 0.0));
+        assert_eq!(heatmap.pixel(2, 0), Color::new(1.0, 0.0, 0.0));
+
+        // Without any difference in counts, every pixel is blue.
+        let heatmap = FrameBuffer::new(2, 2).sample_heatmap();
+        assert_eq!(heatmap.pixel(1, 1), Color::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn mean_variance_test() {
+        let mut image = FrameBuffer::new(2, 1);
+        image.add_sample(0, 0, Color::new(1.0, 1.0, 1.0));
+        assert_eq!(image.mean_variance(0, 0), INFINITY);
+        assert_eq!(image.mean_variance(1, 0), INFINITY);
+
+        // A sample variance of 5/3 over 4 samples, weighted samples not counting.
+        for v in [2.0, 3.0, 4.0] {
+            image.add_sample(0, 0, Color::new(v, v, v));
+        }
+        image.add_weighted_sample(0, 0, Color::new(100.0, 100.0, 100.0), 1.0);
+        assert!((image.mean_variance(0, 0) - 5.0 / 12.0).abs() < 1e-12);
+
+        // Merging an image without samples keeps the estimate.
+        let image = image.merge(FrameBuffer::new(2, 1));
+        assert!((image.mean_variance(0, 0) - 5.0 / 12.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn rgbe_test() {
+        assert_eq!(to_rgbe(Color::new(0.0, 0.0, 0.0)), [0, 0, 0, 0]);
+        assert_eq!(to_rgbe(Color::new(1.0, 0.5, 0.25)), [128, 64, 32, 129]);
+        assert_eq!(to_rgbe(Color::new(0.5, 0.0, 0.0)), [128, 0, 0, 128]);
+        assert_eq!(to_rgbe(Color::new(3.0, 0.0, 0.0)), [192, 0, 0, 130]);
+        assert_eq!(to_rgbe(Color::new(1000.0, 1.0, 0.0)), [250, 0, 0, 138]);
+        assert_eq!(to_rgbe(Color::new(-1.0, 1.0, 0.0)), [0, 128, 0, 129]);
+    }
+
+    #[test]
+    fn hdr_layout_test() {
+        let mut image = FrameBuffer::new(3, 2);
+        image.add_sample(0, 1, Color::new(1.0, 2.0, 3.0));
+        let mut bytes = vec![];
+        image.write_hdr(&mut bytes).unwrap();
+
+        let header = "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 3\n";
+        assert_eq!(&bytes[..header.len()], header.as_bytes());
+        // Flat scanlines of four bytes per pixel, the top row first.
+        let pixels = &bytes[header.len()..];
+        assert_eq!(pixels.len(), 2 * 3 * 4);
+        assert_eq!(pixels[..4], [64, 128, 192, 130]);
+        assert!(pixels[4..].iter().all(|&byte| byte == 0));
+    }
+}
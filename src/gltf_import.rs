@@ -0,0 +1,685 @@
+use std::{cell::RefCell, collections::HashMap, convert::TryInto, fmt, path::Path, sync::Arc};
+
+use ::gltf::{
+    buffer, camera::Projection, image, khr_lights_punctual::Kind, mesh::Mode, texture, Document,
+};
+use nalgebra::Matrix4;
+
+use crate::{
+    aabb::Aabb,
+    bvh::Bvh,
+    camera::{Camera, OrthographicCamera},
+    definitions::{degrees_to_radians, near_zero, PI},
+    image_texture::{srgb_to_linear, ColorSpace, Filter, ImageData, ImageTexture, WrapMode},
+    material::{
+        Dielectric, DiffuseLight, Emissive, Lambertian, Metal, MixMaterial, NormalMapped,
+        VertexTinted,
+    },
+    mesh::Mesh,
+    plane::Disk,
+    scene_graph::{SceneError, SceneGraph, SceneNode},
+    sphere::Sphere,
+    texture::{SolidColor, Texture},
+    transform::Transform,
+    Color, Hittable, HittableList, Material, Point3, Vec3,
+};
+
+/// Defines what can go wrong importing a glTF file.
+#[derive(Debug)]
+pub enum GltfError {
+    /// The file, or a buffer or image it refers to, could not be read or parsed.
+    Gltf(::gltf::Error),
+    /// The file has no scene to import.
+    NoScene,
+    /// A primitive with indices past the end of its vertices.
+    Data(String),
+    /// The nodes could not be put together into a scene graph.
+    Scene(SceneError),
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GltfError::Gltf(error) => write!(f, "{}", error),
+            GltfError::NoScene => write!(f, "glTF file without a scene"),
+            GltfError::Data(message) => write!(f, "invalid glTF data: {}", message),
+            GltfError::Scene(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for GltfError {}
+
+impl From<::gltf::Error> for GltfError {
+    fn from(error: ::gltf::Error) -> Self {
+        GltfError::Gltf(error)
+    }
+}
+
+impl From<SceneError> for GltfError {
+    fn from(error: SceneError) -> Self {
+        GltfError::Scene(error)
+    }
+}
+
+/// Size of the spheres standing in for point and spot lights, relative to the diagonal of the
+/// bounding box of the scene.
+const LIGHT_SIZE: f64 = 0.01;
+/// Distance of the disks standing in for directional lights, relative to the diagonal of the
+/// bounding box of the scene.
+const SUN_DISTANCE: f64 = 10.0;
+/// Angular radius of the disks standing in for directional lights, in degrees.
+const SUN_ANGLE: f64 = 1.0;
+
+/// Defines a scene imported from a glTF 2.0 file: the nodes of its default scene in a scene graph,
+/// and its cameras.
+///
+/// Every node keeps its name and transform, meshes being shared by the nodes placing them.
+/// Metallic-roughness materials become a
+/// [Lambertian] base mixed with a [Metal] by their metallic factor and texture, and with a
+/// [Dielectric] by their transmission, under their normal map. The metal takes the base colour
+/// factor and the roughness factor as its fuzz, textures only tinting the diffuse base. Emissive
+/// materials give off their emission on top of that, and vertex colours tint the whole surface.
+/// Point lights become small glowing spheres of the same intensity and directional lights distant
+/// glowing disks giving the same illuminance, both added as a child named after the node with
+/// " light". Spot lights are approximated by point lights, shining in every direction rather than
+/// only inside their cone.
+pub struct GltfScene {
+    pub graph: SceneGraph,
+    /// The graph flattened, ready to render.
+    pub world: HittableList,
+    /// The perspective cameras, in the order their nodes are visited.
+    pub cameras: Vec<Camera>,
+    pub orthographic_cameras: Vec<OrthographicCamera>,
+}
+
+impl GltfScene {
+    /// Reads a `.gltf` or `.glb` file along with the buffers and images it refers to.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, GltfError> {
+        let (document, buffers, images) = ::gltf::import(path)?;
+        Importer::new(&buffers, &images).import(&document)
+    }
+
+    /// Reads a scene held in memory, whose buffers and images are embedded.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, GltfError> {
+        let (document, buffers, images) = ::gltf::import_slice(bytes)?;
+        Importer::new(&buffers, &images).import(&document)
+    }
+}
+
+/// Defines an image texture as glTF reads it: its colour multiplied by a factor, or a single
+/// channel of it multiplied by a factor.
+struct FactoredTexture {
+    texture: ImageTexture,
+    factor: Color,
+    channel: Option<usize>,
+}
+
+impl Texture for FactoredTexture {
+    fn value(&self, u: f64, v: f64, point: &Point3) -> Color {
+        let color = self.texture.value(u, v, point);
+        match self.channel {
+            Some(channel) => color[channel] * self.factor,
+            None => color.component_mul(&self.factor),
+        }
+    }
+}
+
+/// Returns the first three components of a glTF array, such as a position or a colour factor.
+fn vector3(components: &[f32]) -> Vec3 {
+    Vec3::new(
+        components[0] as f64,
+        components[1] as f64,
+        components[2] as f64,
+    )
+}
+
+/// Returns the name of a glTF node in `graph`: its own, or one made of its `index` for nodes
+/// without a name or with the name of another. Made up names taken by other nodes are numbered
+/// until one is free.
+fn unique_name(graph: &SceneGraph, name: Option<&str>, index: usize) -> String {
+    let base = match name {
+        Some(name) if graph.node(name).is_none() => return name.to_string(),
+        Some(name) => format!("{} {}", name, index),
+        None => format!("node {}", index),
+    };
+    let mut unique = base.clone();
+    let mut count = 1;
+    while graph.node(&unique).is_some() {
+        count += 1;
+        unique = format!("{} {}", base, count);
+    }
+    unique
+}
+
+/// Returns the pixels of a decoded glTF image, grey ones spread over all three channels.
+fn decode(data: &image::Data, color_space: ColorSpace) -> ImageData {
+    let (channels, size) = match data.format {
+        image::Format::R8 => (1, 1),
+        image::Format::R8G8 => (2, 1),
+        image::Format::R8G8B8 => (3, 1),
+        image::Format::R8G8B8A8 => (4, 1),
+        image::Format::R16 => (1, 2),
+        image::Format::R16G16 => (2, 2),
+        image::Format::R16G16B16 => (3, 2),
+        image::Format::R16G16B16A16 => (4, 2),
+        image::Format::R32G32B32FLOAT => (3, 4),
+        image::Format::R32G32B32A32FLOAT => (4, 4),
+    };
+    let value = |bytes: &[u8]| {
+        let c = match size {
+            1 => bytes[0] as f64 / 255.0,
+            2 => u16::from_ne_bytes(bytes.try_into().unwrap()) as f64 / 65535.0,
+            _ => f32::from_ne_bytes(bytes.try_into().unwrap()) as f64,
+        };
+        // Floating point images are stored linearly.
+        match color_space {
+            ColorSpace::Srgb if size < 4 => srgb_to_linear(c),
+            _ => c,
+        }
+    };
+    let pixels = data
+        .pixels
+        .chunks_exact(channels * size)
+        .map(|pixel| {
+            let channel = |i: usize| value(&pixel[i * size..(i + 1) * size]);
+            if channels < 3 {
+                Color::repeat(channel(0))
+            } else {
+                Color::new(channel(0), channel(1), channel(2))
+            }
+        })
+        .collect();
+    ImageData::new(data.width as usize, data.height as usize, pixels)
+}
+
+/// Defines the state of an import: the data of the file and the materials and images already
+/// converted.
+struct Importer<'a> {
+    buffers: &'a [buffer::Data],
+    images: &'a [image::Data],
+    decoded: RefCell<HashMap<(usize, ColorSpace), Arc<ImageData>>>,
+    materials: RefCell<HashMap<Option<usize>, Arc<dyn Material>>>,
+}
+
+impl<'a> Importer<'a> {
+    fn new(buffers: &'a [buffer::Data], images: &'a [image::Data]) -> Self {
+        Self {
+            buffers,
+            images,
+            decoded: RefCell::default(),
+            materials: RefCell::default(),
+        }
+    }
+
+    /// Returns the scene made of the default scene of `document`, or its first one.
+    fn import(&self, document: &Document) -> Result<GltfScene, GltfError> {
+        let scene = document
+            .default_scene()
+            .or_else(|| document.scenes().next())
+            .ok_or(GltfError::NoScene)?;
+
+        let mut graph = SceneGraph::new();
+        let mut meshes = HashMap::<usize, Option<Arc<dyn Hittable>>>::new();
+        let mut placed = vec![];
+        let mut stack = scene
+            .nodes()
+            .map(|node| (node, None, Transform::identity()))
+            .collect::<Vec<_>>();
+        // Nodes are taken from the end of the stack, which is kept reversed to visit them in order.
+        stack.reverse();
+        while let Some((node, parent, parent_transform)) = stack.pop() {
+            let local = node.transform().matrix();
+            // Nodes scaled down to nothing are left out along with their children.
+            let local = match Transform::from_matrix(Matrix4::from_fn(|r, c| local[c][r] as f64)) {
+                Some(local) => local,
+                None => continue,
+            };
+            let name = unique_name(&graph, node.name(), node.index());
+            let mut scene_node = SceneNode::new(name.clone()).with_transform(local);
+            if let Some(mesh) = node.mesh() {
+                let bvh = match meshes.get(&mesh.index()) {
+                    Some(bvh) => bvh.clone(),
+                    None => {
+                        let bvh = self.mesh_bvh(&mesh)?;
+                        meshes.insert(mesh.index(), bvh.clone());
+                        bvh
+                    }
+                };
+                if let Some(bvh) = bvh {
+                    scene_node = scene_node.with_object(bvh);
+                }
+            }
+            graph.add(scene_node, parent.as_deref())?;
+
+            let transform = local.then(&parent_transform);
+            let children = node.children().collect::<Vec<_>>();
+            stack.extend(
+                children
+                    .into_iter()
+                    .rev()
+                    .map(|child| (child, Some(name.clone()), transform)),
+            );
+            placed.push((node, name, transform));
+        }
+
+        let bounds = Aabb::from_points(
+            graph
+                .placed_objects()
+                .iter()
+                .filter_map(|object| object.bounding_box())
+                .flat_map(|bounds| vec![bounds.min, bounds.max]),
+        );
+        let (center, diagonal) = bounds.map_or((Point3::zeros(), 1.0), |bounds| {
+            (
+                bounds.centroid(),
+                (bounds.max - bounds.min).norm().max(1e-3),
+            )
+        });
+
+        let mut cameras = vec![];
+        let mut orthographic_cameras = vec![];
+        for (node, name, transform) in &placed {
+            let position = transform.point(&Point3::zeros());
+            let focus = position + transform.vector(&Vec3::new(0.0, 0.0, -1.0));
+            let vup = transform.vector(&Vec3::new(0.0, 1.0, 0.0));
+            if let Some(camera) = node.camera() {
+                match camera.projection() {
+                    Projection::Perspective(perspective) => {
+                        let mut builder = Camera::builder(position, focus)
+                            .vup(vup)
+                            .vfov((perspective.yfov() as f64).to_degrees());
+                        if let Some(aspect_ratio) = perspective.aspect_ratio() {
+                            builder = builder.aspect_ratio(aspect_ratio as f64);
+                        }
+                        cameras.push(builder.build());
+                    }
+                    Projection::Orthographic(orthographic) => {
+                        orthographic_cameras.push(OrthographicCamera::new(
+                            &position,
+                            &focus,
+                            &vup,
+                            2.0 * orthographic.ymag() as f64,
+                            (orthographic.xmag() / orthographic.ymag()) as f64,
+                        ))
+                    }
+                }
+            }
+
+            if let Some(light) = node.light() {
+                let intensity = light.intensity() as f64 * vector3(&light.color());
+                let object: Arc<dyn Hittable> = match light.kind() {
+                    Kind::Directional => {
+                        let direction = (focus - position).normalize();
+                        let distance = SUN_DISTANCE * diagonal;
+                        let angle = degrees_to_radians(SUN_ANGLE);
+                        let radius = distance * angle.tan();
+                        // The illuminance spread over the solid angle of the disk.
+                        let radiance = intensity / (PI * angle.tan().powi(2));
+                        Arc::new(Disk::new(
+                            center - distance * direction,
+                            direction,
+                            radius,
+                            Arc::new(DiffuseLight::new(radiance)),
+                        ))
+                    }
+                    // The glowing sphere has no cone to restrict a spot light to.
+                    Kind::Point | Kind::Spot { .. } => {
+                        let radius = LIGHT_SIZE * diagonal;
+                        // Seen from afar, a sphere of radiance L has an intensity of L π r².
+                        let radiance = intensity / (PI * radius * radius);
+                        Arc::new(Sphere::new(
+                            position,
+                            radius,
+                            Arc::new(DiffuseLight::new(radiance)),
+                        ))
+                    }
+                };
+                // Placed in the world whatever the scale of the node, but following it when moved.
+                let light_node = SceneNode::new(unique_name(
+                    &graph,
+                    Some(&format!("{} light", name)),
+                    node.index(),
+                ))
+                .with_transform(transform.inverse())
+                .with_object(object);
+                graph.add(light_node, Some(name))?;
+            }
+        }
+
+        Ok(GltfScene {
+            world: graph.flatten(),
+            graph,
+            cameras,
+            orthographic_cameras,
+        })
+    }
+
+    /// Returns the triangles of `mesh` in a bounding volume hierarchy, or None if it has none.
+    /// Primitives other than lists of triangles are left out.
+    fn mesh_bvh(&self, mesh: &::gltf::Mesh) -> Result<Option<Arc<dyn Hittable>>, GltfError> {
+        let mut triangles = vec![];
+        for primitive in mesh.primitives() {
+            if primitive.mode() != Mode::Triangles {
+                continue;
+            }
+            let reader =
+                primitive.reader(|buffer| self.buffers.get(buffer.index()).map(|data| &data[..]));
+            let positions = match reader.read_positions() {
+                Some(positions) => positions.map(|p| vector3(&p)).collect::<Vec<Point3>>(),
+                None => continue,
+            };
+            let indices = match reader.read_indices() {
+                Some(indices) => indices.into_u32().map(|i| i as usize).collect(),
+                None => (0..positions.len()).collect::<Vec<_>>(),
+            };
+            if let Some(index) = indices.iter().find(|&&i| i >= positions.len()) {
+                return Err(GltfError::Data(format!(
+                    "index {} of a primitive with {} vertices",
+                    index,
+                    positions.len()
+                )));
+            }
+            let faces = indices.chunks_exact(3).map(|face| face.to_vec()).collect();
+
+            let mut mesh = Mesh::new(positions, faces);
+            if let Some(normals) = reader.read_normals() {
+                mesh = mesh.with_normals(normals.map(|n| vector3(&n)).collect());
+            }
+            if let Some(uvs) = reader.read_tex_coords(0) {
+                // glTF images start at the top, with v going down.
+                mesh = mesh.with_uvs(
+                    uvs.into_f32()
+                        .map(|[u, v]| (u as f64, 1.0 - v as f64))
+                        .collect(),
+                );
+            }
+            let mut material = self.material(&primitive.material());
+            if let Some(colors) = reader.read_colors(0) {
+                mesh = mesh.with_colors(colors.into_rgb_f32().map(|c| vector3(&c)).collect());
+                material = Arc::new(VertexTinted::new(material));
+            }
+            triangles.extend(mesh.to_triangles(material));
+        }
+        Ok(match triangles.is_empty() {
+            true => None,
+            false => Some(Arc::new(Bvh::new(triangles))),
+        })
+    }
+
+    /// Returns the material standing in for `material`.
+    fn material(&self, material: &::gltf::Material) -> Arc<dyn Material> {
+        if let Some(converted) = self.materials.borrow().get(&material.index()) {
+            return converted.clone();
+        }
+
+        let pbr = material.pbr_metallic_roughness();
+        let base_color = vector3(&pbr.base_color_factor());
+        let roughness = pbr.roughness_factor() as f64;
+        let metallic = pbr.metallic_factor() as f64;
+
+        let albedo = self.texture(
+            pbr.base_color_texture().map(|info| info.texture()),
+            base_color,
+            ColorSpace::Srgb,
+            None,
+        );
+        let mut surface: Arc<dyn Material> = Arc::new(Lambertian::textured(albedo));
+        if metallic > 0.0 {
+            let metal = Arc::new(Metal::new(base_color, roughness));
+            surface = match pbr.metallic_roughness_texture() {
+                // Metalness is read from the blue channel.
+                Some(info) => {
+                    let weight = self.texture(
+                        Some(info.texture()),
+                        Color::repeat(metallic),
+                        ColorSpace::Linear,
+                        Some(2),
+                    );
+                    Arc::new(MixMaterial::textured(surface, metal, weight))
+                }
+                None if metallic >= 1.0 => metal,
+                None => Arc::new(MixMaterial::new(surface, metal, metallic)),
+            };
+        }
+        if let Some(transmission) = material.transmission() {
+            let weight = transmission.transmission_factor() as f64;
+            if weight > 0.0 {
+                let glass = Arc::new(
+                    Dielectric::new(material.ior().unwrap_or(1.5) as f64).with_roughness(roughness),
+                );
+                surface = match weight >= 1.0 {
+                    true => glass,
+                    false => Arc::new(MixMaterial::new(surface, glass, weight)),
+                };
+            }
+        }
+        if let Some(normal) = material.normal_texture() {
+            let map = self.texture(
+                Some(normal.texture()),
+                Color::new(1.0, 1.0, 1.0),
+                ColorSpace::Linear,
+                None,
+            );
+            surface = Arc::new(NormalMapped::new(surface, map, normal.scale() as f64));
+        }
+        let emission = vector3(&material.emissive_factor())
+            * material.emissive_strength().unwrap_or(1.0) as f64;
+        if !near_zero(emission) {
+            let emit = self.texture(
+                material.emissive_texture().map(|info| info.texture()),
+                emission,
+                ColorSpace::Srgb,
+                None,
+            );
+            surface = Arc::new(Emissive::new(surface, emit));
+        }
+
+        self.materials
+            .borrow_mut()
+            .insert(material.index(), surface.clone());
+        surface
+    }
+
+    /// Returns the image of `texture` multiplied by `factor`, or only `factor` without a texture.
+    fn texture(
+        &self,
+        texture: Option<texture::Texture>,
+        factor: Color,
+        color_space: ColorSpace,
+        channel: Option<usize>,
+    ) -> Arc<dyn Texture> {
+        let texture = match texture {
+            Some(texture) => texture,
+            None => return Arc::new(SolidColor::new(factor)),
+        };
+        let index = texture.source().index();
+        let image = self
+            .decoded
+            .borrow_mut()
+            .entry((index, color_space))
+            .or_insert_with(|| Arc::new(decode(&self.images[index], color_space)))
+            .clone();
+
+        let sampler = texture.sampler();
+        let wrap = match sampler.wrap_s() {
+            texture::WrappingMode::ClampToEdge => WrapMode::Clamp,
+            texture::WrappingMode::MirroredRepeat => WrapMode::Mirror,
+            texture::WrappingMode::Repeat => WrapMode::Repeat,
+        };
+        let filter = match sampler.mag_filter() {
+            Some(texture::MagFilter::Nearest) => Filter::Nearest,
+            _ => Filter::Bilinear,
+        };
+        Arc::new(FactoredTexture {
+            texture: ImageTexture::new(image).with_wrap(wrap).with_filter(filter),
+            factor,
+            channel,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Ray;
+
+    /// Returns a binary glTF file holding `json` and `bin`.
+    fn glb(json: &str, bin: &[u8]) -> Vec<u8> {
+        let mut json = json.as_bytes().to_vec();
+        json.resize(json.len().div_ceil(4) * 4, b' ');
+        let mut bin = bin.to_vec();
+        bin.resize(bin.len().div_ceil(4) * 4, 0);
+
+        let mut bytes = b"glTF".to_vec();
+        bytes.extend(2u32.to_le_bytes());
+        bytes.extend((12 + 8 + json.len() as u32 + 8 + bin.len() as u32).to_le_bytes());
+        bytes.extend((json.len() as u32).to_le_bytes());
+        bytes.extend(b"JSON");
+        bytes.extend(json);
+        bytes.extend((bin.len() as u32).to_le_bytes());
+        bytes.extend(b"BIN\0");
+        bytes.extend(bin);
+        bytes
+    }
+
+    #[test]
+    fn import_test() {
+        let json = r#"{
+            "asset": {"version": "2.0"},
+            "extensionsUsed": ["KHR_lights_punctual"],
+            "extensions": {"KHR_lights_punctual": {"lights": [{"type": "point", "intensity": 10}]}},
+            "scene": 0,
+            "scenes": [{"nodes": [0, 2]}],
+            "nodes": [
+                {"name": "table", "translation": [0, 0, -2], "children": [1]},
+                {"mesh": 0, "scale": [-2, 2, 2]},
+                {"translation": [0, 1, 0], "children": [3, 4]},
+                {"camera": 0},
+                {"translation": [0, 2, -2], "extensions": {"KHR_lights_punctual": {"light": 0}}}
+            ],
+            "cameras": [{"type": "perspective",
+                         "perspective": {"yfov": 0.8, "znear": 0.1, "aspectRatio": 1.5}}],
+            "meshes": [{"primitives": [{"attributes": {"POSITION": 0}, "material": 0}]}],
+            "materials": [{"pbrMetallicRoughness": {"baseColorFactor": [0.8, 0.2, 0.2, 1],
+                                                    "metallicFactor": 0}}],
+            "buffers": [{"byteLength": 36}],
+            "bufferViews": [{"buffer": 0, "byteLength": 36}],
+            "accessors": [{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                           "min": [-1, -1, 0], "max": [1, 1, 0]}]
+        }"#;
+        let bin = [-1f32, -1.0, 0.0, 1.0, -1.0, 0.0, 0.0, 1.0, 0.0]
+            .iter()
+            .flat_map(|c| c.to_le_bytes())
+            .collect::<Vec<_>>();
+        let scene = GltfScene::from_slice(&glb(json, &bin)).unwrap();
+
+        // The triangle is scaled by its node and moved by the parent, the mirroring undone.
+        let ray = Ray::new(Point3::new(1.5, -1.5, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = scene.world.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 2.0).abs() < 1e-9);
+        assert!(rec.front_face);
+        assert!((rec.material.albedo(&rec) - Color::new(0.8, 0.2, 0.2)).norm() < 1e-6);
+
+        assert_eq!(scene.graph.roots(), ["table", "node 2"]);
+        assert_eq!(scene.graph.children("table"), ["node 1"]);
+        assert_eq!(scene.graph.parent("node 4 light"), Some("node 4"));
+
+        assert_eq!(scene.cameras.len(), 1);
+        let ray = scene.cameras[0].get_ray(0.5, 0.5);
+        assert!((ray.origin() - Point3::new(0.0, 1.0, 0.0)).norm() < 1e-9);
+        assert!((ray.direction().normalize() - Vec3::new(0.0, 0.0, -1.0)).norm() < 1e-9);
+
+        let ray = Ray::new(Point3::new(0.0, 10.0, -2.0), Vec3::new(0.0, -1.0, 0.0));
+        let rec = scene.world.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.point - Point3::new(0.0, 3.0, -2.0)).norm() < 0.1);
+        assert!(rec.material.emitted(&rec).x > 0.0);
+
+        let without_scene = json
+            .replace(r#""scene": 0,"#, "")
+            .replace(r#"[{"nodes": [0, 2]}]"#, "[]");
+        assert!(matches!(
+            GltfScene::from_slice(&glb(&without_scene, &bin)),
+            Err(GltfError::NoScene)
+        ));
+        assert!(matches!(
+            GltfScene::from_slice(b"not glTF"),
+            Err(GltfError::Gltf(_))
+        ));
+    }
+
+    #[test]
+    fn material_test() {
+        let json = r#"{
+            "asset": {"version": "2.0"},
+            "extensionsUsed": ["KHR_lights_punctual"],
+            "extensions": {"KHR_lights_punctual": {"lights": [{"type": "point"}]}},
+            "scenes": [{"nodes": [0, 1]}],
+            "nodes": [{"mesh": 0}, {"extensions": {"KHR_lights_punctual": {"light": 0}}}],
+            "meshes": [{"primitives": [{"attributes": {"POSITION": 0, "COLOR_0": 1},
+                                        "material": 0}]}],
+            "materials": [{"pbrMetallicRoughness": {"baseColorFactor": [0.5, 0.5, 0.5, 1],
+                                                    "metallicFactor": 0},
+                           "emissiveFactor": [1, 0.5, 0]}],
+            "buffers": [{"byteLength": 72}],
+            "bufferViews": [{"buffer": 0, "byteLength": 36},
+                            {"buffer": 0, "byteOffset": 36, "byteLength": 36}],
+            "accessors": [
+                {"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                 "min": [-1, -1, 0], "max": [1, 1, 0]},
+                {"bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC3"}
+            ]
+        }"#;
+        let bin = [-1f32, -1.0, 0.0, 1.0, -1.0, 0.0, 0.0, 1.0, 0.0]
+            .iter()
+            .chain(&[1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0])
+            .flat_map(|c| c.to_le_bytes())
+            .collect::<Vec<_>>();
+        let scene = GltfScene::from_slice(&glb(json, &bin)).unwrap();
+        let hit = |x: f64, y: f64| {
+            let ray = Ray::new(Point3::new(x, y, 5.0), Vec3::new(0.0, 0.0, -1.0));
+            scene.world.hit(&ray, 0.001, f64::INFINITY).unwrap()
+        };
+
+        // Vertex colours blend across the face and tint the base colour.
+        let rec = hit(0.0, -1.0 / 3.0);
+        assert!((rec.material.albedo(&rec) - Color::repeat(0.5 / 3.0)).norm() < 1e-6);
+        let rec = hit(-0.9, -0.95);
+        let albedo = rec.material.albedo(&rec);
+        assert!(albedo.x > 0.4 && albedo.y < 0.1 && albedo.z < 0.1);
+
+        // The emission comes on top of a surface that still scatters light.
+        assert!((rec.material.emitted(&rec) - Color::new(1.0, 0.5, 0.0)).norm() < 1e-6);
+        let ray = Ray::new(rec.point, rec.normal);
+        assert!(rec.material.scatter(&ray, &rec, &ray).is_some());
+
+        let spot = json.replace(
+            r#"{"type": "point"}"#,
+            r#"{"type": "spot", "spot": {"outerConeAngle": 0.5}}"#,
+        );
+        let scene = GltfScene::from_slice(&glb(&spot, &bin)).unwrap();
+        assert!(scene.graph.node("node 1 light").is_some());
+    }
+
+    #[test]
+    fn names_test() {
+        let json = r#"{
+            "asset": {"version": "2.0"},
+            "extensionsUsed": ["KHR_lights_punctual"],
+            "extensions": {"KHR_lights_punctual": {"lights": [{"type": "point"}]}},
+            "scenes": [{"nodes": [0, 2, 3]}],
+            "nodes": [
+                {"name": "node 1", "children": [1]},
+                {},
+                {"name": "lamp", "extensions": {"KHR_lights_punctual": {"light": 0}}},
+                {"name": "lamp light"}
+            ]
+        }"#;
+        let scene = GltfScene::from_slice(&glb(json, &[])).unwrap();
+
+        // Made up names already given to other nodes are numbered further.
+        assert_eq!(scene.graph.children("node 1"), ["node 1 2"]);
+        assert_eq!(scene.graph.children("lamp"), ["lamp light 2"]);
+        assert!(scene.graph.node("lamp light").is_some());
+    }
+}
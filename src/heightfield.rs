@@ -0,0 +1,362 @@
+use std::{path::Path, sync::Arc};
+
+use image::{
+    error::{ParameterError, ParameterErrorKind},
+    ImageError,
+};
+
+use crate::{
+    aabb::Aabb,
+    color::luminance,
+    image_texture::{ColorSpace, ImageData},
+    HitRecord, Hittable, Material, Point3, Ray, Vec3, INFINITY,
+};
+
+/// Returns the distance along `ray` to the triangle (`p0`, `p1`, `p2`) and the barycentric
+/// coordinates of the hit relative to `p1` and `p2`, following Möller and Trumbore.
+pub fn intersect_triangle(
+    ray: &Ray,
+    p0: &Point3,
+    p1: &Point3,
+    p2: &Point3,
+) -> Option<(f64, f64, f64)> {
+    let (edge1, edge2) = (p1 - p0, p2 - p0);
+    let h = ray.direction().cross(&edge2);
+    let det = edge1.dot(&h);
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    let s = ray.origin() - p0;
+    let b1 = s.dot(&h) / det;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+    let q = s.cross(&edge1);
+    let b2 = ray.direction().dot(&q) / det;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+    Some((edge2.dot(&q) / det, b1, b2))
+}
+
+/// Defines a terrain surface from a regular grid of heights, each cell split into two triangles
+/// with normals interpolated across them. Rays walk through the cells they cross, in order, so
+/// only a thin line of cells is tested however large the grid is.
+pub struct HeightField {
+    heights: Vec<f64>,
+    normals: Vec<Vec3>,
+    resolution: (usize, usize),
+    corner: Point3,
+    size: Vec3,
+    /// Lowest and highest points of the surface.
+    bounds: (f64, f64),
+    material: Arc<dyn Material>,
+}
+
+impl HeightField {
+    /// Creates the terrain covering `size.x` by `size.z` from `corner` along X and Z, from
+    /// `resolution.0` by `resolution.1` samples stored row by row along +Z. Heights are scaled by
+    /// `size.y` and added to the height of `corner`. Returns None for fewer than 2 × 2 samples or
+    /// if there are not as many heights as samples.
+    pub fn new(
+        heights: Vec<f64>,
+        resolution: (usize, usize),
+        corner: Point3,
+        size: Vec3,
+        material: Arc<dyn Material>,
+    ) -> Option<Self> {
+        let (nx, nz) = resolution;
+        if nx < 2 || nz < 2 || Some(heights.len()) != nx.checked_mul(nz) {
+            return None;
+        }
+        let heights = heights
+            .into_iter()
+            .map(|h| corner.y + h * size.y)
+            .collect::<Vec<f64>>();
+        let bounds = heights
+            .iter()
+            .fold((INFINITY, -INFINITY), |(low, high), &h| {
+                (low.min(h), high.max(h))
+            });
+
+        let mut field = Self {
+            heights,
+            normals: vec![],
+            resolution,
+            corner,
+            size,
+            bounds,
+            material,
+        };
+        field.normals = (0..nz)
+            .flat_map(|j| (0..nx).map(move |i| (i, j)))
+            .map(|(i, j)| field.vertex_normal(i, j))
+            .collect();
+        Some(field)
+    }
+
+    /// Creates the terrain from the luminance of a grayscale image, its rows going along +Z.
+    /// Returns None for images narrower or shorter than 2 pixels.
+    pub fn from_image(
+        image: &ImageData,
+        corner: Point3,
+        size: Vec3,
+        material: Arc<dyn Material>,
+    ) -> Option<Self> {
+        let heights = (0..image.height())
+            .flat_map(|y| (0..image.width()).map(move |x| (x, y)))
+            .map(|(x, y)| luminance(&image.texel(x, y)))
+            .collect();
+        Self::new(
+            heights,
+            (image.width(), image.height()),
+            corner,
+            size,
+            material,
+        )
+    }
+
+    /// Decodes a grayscale height map into a terrain, as with [from_image](Self::from_image).
+    pub fn open(
+        path: impl AsRef<Path>,
+        corner: Point3,
+        size: Vec3,
+        material: Arc<dyn Material>,
+    ) -> Result<Self, ImageError> {
+        let image = ImageData::open(path, ColorSpace::Linear)?;
+        Self::from_image(&image, corner, size, material).ok_or_else(|| {
+            ImageError::Parameter(ParameterError::from_kind(ParameterErrorKind::Generic(
+                "a height field needs at least 2 × 2 samples".to_string(),
+            )))
+        })
+    }
+
+    /// Returns the world size of a cell along X and Z.
+    fn cell_size(&self) -> (f64, f64) {
+        (
+            self.size.x / (self.resolution.0 - 1) as f64,
+            self.size.z / (self.resolution.1 - 1) as f64,
+        )
+    }
+
+    fn height(&self, i: usize, j: usize) -> f64 {
+        self.heights[j * self.resolution.0 + i]
+    }
+
+    fn vertex(&self, i: usize, j: usize) -> Point3 {
+        let (dx, dz) = self.cell_size();
+        Point3::new(
+            self.corner.x + i as f64 * dx,
+            self.height(i, j),
+            self.corner.z + j as f64 * dz,
+        )
+    }
+
+    /// Returns the normal at a sample, from the slopes to its neighbours.
+    fn vertex_normal(&self, i: usize, j: usize) -> Vec3 {
+        let (nx, nz) = self.resolution;
+        let (dx, dz) = self.cell_size();
+        let (i0, i1) = (i.saturating_sub(1), (i + 1).min(nx - 1));
+        let (j0, j1) = (j.saturating_sub(1), (j + 1).min(nz - 1));
+        let slope_x = (self.height(i1, j) - self.height(i0, j)) / ((i1 - i0) as f64 * dx);
+        let slope_z = (self.height(i, j1) - self.height(i, j0)) / ((j1 - j0) as f64 * dz);
+        Vec3::new(-slope_x, 1.0, -slope_z).normalize()
+    }
+
+    /// Returns the nearest hit between `t_min` and `t_max` with the two triangles of cell (`i`,
+    /// `j`).
+    fn cell_hit(&self, ray: &Ray, i: usize, j: usize, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        // Both triangles are wound so that their normal points up.
+        let triangles = [
+            [(i, j), (i + 1, j + 1), (i + 1, j)],
+            [(i, j), (i, j + 1), (i + 1, j + 1)],
+        ];
+        let mut nearest: Option<(f64, usize, f64, f64)> = None;
+        for (index, triangle) in triangles.iter().enumerate() {
+            let [p0, p1, p2] = triangle.map(|(i, j)| self.vertex(i, j));
+            if let Some((t, b1, b2)) = intersect_triangle(ray, &p0, &p1, &p2) {
+                let closest = nearest.map_or(t_max, |(t, ..)| t);
+                if t_min <= t && t <= closest {
+                    nearest = Some((t, index, b1, b2));
+                }
+            }
+        }
+        let (t, index, b1, b2) = nearest?;
+        let triangle = triangles[index];
+
+        let [p0, p1, p2] = triangle.map(|(i, j)| self.vertex(i, j));
+        let [n0, n1, n2] = triangle.map(|(i, j)| self.normals[j * self.resolution.0 + i]);
+        let geometric_normal = (p1 - p0).cross(&(p2 - p0)).normalize();
+        let shading_normal = ((1.0 - b1 - b2) * n0 + b1 * n1 + b2 * n2).normalize();
+
+        let mut rec = HitRecord::new(ray, t, &geometric_normal, self.material.clone());
+        rec.normal = if rec.front_face {
+            shading_normal
+        } else {
+            -shading_normal
+        };
+        let (slope_x, slope_z) = (
+            -shading_normal.x / shading_normal.y,
+            -shading_normal.z / shading_normal.y,
+        );
+        let uv = (
+            (rec.point.x - self.corner.x) / self.size.x,
+            (rec.point.z - self.corner.z) / self.size.z,
+        );
+        Some(rec.with_uv(
+            uv,
+            (
+                self.size.x * Vec3::new(1.0, slope_x, 0.0),
+                self.size.z * Vec3::new(0.0, slope_z, 1.0),
+            ),
+        ))
+    }
+}
+
+impl Hittable for HeightField {
+    /// Provides a definition of hit() for height fields, walking the grid with a 2D DDA.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (nx, nz) = self.resolution;
+        let (dx, dz) = self.cell_size();
+        // The ray in grid coordinates along X and Z, where cells are one unit wide.
+        let origin = Vec3::new(
+            (ray.origin().x - self.corner.x) / dx,
+            ray.origin().y,
+            (ray.origin().z - self.corner.z) / dz,
+        );
+        let direction = Vec3::new(
+            ray.direction().x / dx,
+            ray.direction().y,
+            ray.direction().z / dz,
+        );
+
+        // Clips the ray to the bounding box of the surface.
+        let (mut t0, mut t1) = (t_min, t_max);
+        let low = Vec3::new(0.0, self.bounds.0, 0.0);
+        let high = Vec3::new((nx - 1) as f64, self.bounds.1, (nz - 1) as f64);
+        for axis in 0..3 {
+            if direction[axis].abs() < 1e-12 {
+                if origin[axis] < low[axis] || origin[axis] > high[axis] {
+                    return None;
+                }
+                continue;
+            }
+            let (near, far) = (
+                (low[axis] - origin[axis]) / direction[axis],
+                (high[axis] - origin[axis]) / direction[axis],
+            );
+            t0 = t0.max(near.min(far));
+            t1 = t1.min(near.max(far));
+            if t0 > t1 {
+                return None;
+            }
+        }
+
+        let start = origin + t0 * direction;
+        let cell = |c: f64, n: usize| (c.floor().max(0.0) as usize).min(n - 2);
+        let (mut i, mut j) = (cell(start.x, nx), cell(start.z, nz));
+        let step = |d: f64| if d > 0.0 { 1 } else { -1 };
+        let (step_x, step_z) = (step(direction.x), step(direction.z));
+        // Distances along the ray to the next cell boundary and between two boundaries.
+        let next_boundary = |c: usize, o: f64, d: f64| {
+            if d.abs() < 1e-12 {
+                INFINITY
+            } else {
+                let boundary = if d > 0.0 { c + 1 } else { c } as f64;
+                (boundary - o) / d
+            }
+        };
+        let (mut next_x, mut next_z) = (
+            next_boundary(i, origin.x, direction.x),
+            next_boundary(j, origin.z, direction.z),
+        );
+        let (delta_x, delta_z) = (1.0 / direction.x.abs(), 1.0 / direction.z.abs());
+
+        let mut t_enter = t0;
+        loop {
+            // Skips the triangles of cells the ray passes entirely above or below.
+            let t_exit = next_x.min(next_z).min(t1);
+            let (y0, y1) = (ray.at(t_enter).y, ray.at(t_exit).y);
+            let (low, high) = [(i, j), (i + 1, j), (i, j + 1), (i + 1, j + 1)]
+                .iter()
+                .map(|&(i, j)| self.height(i, j))
+                .fold((INFINITY, -INFINITY), |(low, high), h| {
+                    (low.min(h), high.max(h))
+                });
+            if y0.max(y1) >= low && y0.min(y1) <= high {
+                if let Some(rec) = self.cell_hit(ray, i, j, t_min, t_max) {
+                    return Some(rec);
+                }
+            }
+            if t_exit >= t1 {
+                return None;
+            }
+            t_enter = t_exit;
+            if next_x < next_z {
+                i = i.checked_add_signed(step_x).filter(|&i| i < nx - 1)?;
+                next_x += delta_x;
+            } else {
+                j = j.checked_add_signed(step_z).filter(|&j| j < nz - 1)?;
+                next_z += delta_z;
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let (low, high) = self.bounds;
+        Some(Aabb::new(
+            Point3::new(self.corner.x, low, self.corner.z),
+            Point3::new(
+                self.corner.x + self.size.x,
+                high,
+                self.corner.z + self.size.z,
+            ),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+
+    #[test]
+    fn heightfield_test() {
+        let material = Arc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
+        // A ramp rising by one along X over a 4 × 4 area.
+        let heights = (0..5)
+            .flat_map(|_| (0..5).map(|i| i as f64 / 4.0))
+            .collect();
+        let field = HeightField::new(
+            heights,
+            (5, 5),
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(4.0, 1.0, 4.0),
+            material,
+        )
+        .unwrap();
+
+        let down = Ray::new(Point3::new(2.5, 5.0, 1.5), Vec3::new(0.0, -1.0, 0.0));
+        let rec = field.hit(&down, 0.001, INFINITY).unwrap();
+        assert!((rec.point.y - 0.625).abs() < 1e-9);
+        let expected = Vec3::new(-0.25, 1.0, 0.0).normalize();
+        assert!((rec.normal - expected).norm() < 1e-9);
+
+        // A ray going along the ramp, below its top, hits it further away.
+        let across = Ray::new(Point3::new(-1.0, 0.75, 0.5), Vec3::new(1.0, 0.0, 0.0));
+        let rec = field.hit(&across, 0.001, INFINITY).unwrap();
+        assert!((rec.point.x - 3.0).abs() < 1e-9);
+        let outside = Ray::new(Point3::new(-1.0, 0.75, 5.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(field.hit(&outside, 0.001, INFINITY).is_none());
+    }
+
+    #[test]
+    fn small_heightfield_test() {
+        let material = Arc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
+        let (corner, size) = (Point3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 1.0));
+        let line = ImageData::new(1, 3, vec![Vec3::new(0.5, 0.5, 0.5); 3]);
+        assert!(HeightField::from_image(&line, corner, size, material.clone()).is_none());
+        assert!(HeightField::new(vec![0.0; 3], (2, 2), corner, size, material.clone()).is_none());
+        assert!(HeightField::new(vec![0.0; 4], (2, 2), corner, size, material).is_some());
+    }
+}
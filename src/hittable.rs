@@ -1,6 +1,8 @@
 use std::sync::Arc;
 
-use crate::{Color, Lambertian, Material, Point3, Ray, Vec3};
+use crate::{
+    aabb::Aabb, definitions::near_zero, Color, Lambertian, Material, Point3, Ray, Vec3, INFINITY,
+};
 
 /// Defines a record data-structure to store the information about Rays hitting multiple objects.
 #[derive(Clone)]
@@ -8,8 +10,20 @@ pub struct HitRecord {
     pub point: Point3,
     pub normal: Vec3,
     pub t: f64,
+    pub u: f64,
+    pub v: f64,
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
     pub front_face: bool,
     pub material: Arc<dyn Material>,
+    /// Colour of the surface at the hit, interpolated from the vertex colours of meshes, that of
+    /// the point on point clouds and white elsewhere. See
+    /// [VertexTinted](crate::material::VertexTinted).
+    pub color: Color,
+    /// Width of the fibre at the hit on [curves](crate::curve::Curve), zero on surfaces.
+    pub width: f64,
+    /// Index of the hit object in the outermost [HittableList](crate::HittableList).
+    pub object_id: usize,
 }
 
 impl HitRecord {
@@ -19,8 +33,45 @@ impl HitRecord {
             point: Point3::new(0.0, 0.0, 0.0),
             normal: Vec3::new(0.0, 0.0, 0.0),
             t: 0.0,
+            u: 0.0,
+            v: 0.0,
+            dpdu: Vec3::new(0.0, 0.0, 0.0),
+            dpdv: Vec3::new(0.0, 0.0, 0.0),
             front_face: false,
             material: Arc::new(Lambertian::new(Color::new(0.0, 0.0, 0.0))),
+            color: Color::new(1.0, 1.0, 1.0),
+            width: 0.0,
+            object_id: 0,
+        }
+    }
+    /// Creates the record of `ray` hitting a surface at `t`, with the normal facing the ray.
+    pub fn new(ray: &Ray, t: f64, outward_normal: &Vec3, material: Arc<dyn Material>) -> Self {
+        let mut rec = Self {
+            point: ray.at(t),
+            normal: *outward_normal,
+            t,
+            u: 0.0,
+            v: 0.0,
+            dpdu: Vec3::new(0.0, 0.0, 0.0),
+            dpdv: Vec3::new(0.0, 0.0, 0.0),
+            front_face: true,
+            material,
+            color: Color::new(1.0, 1.0, 1.0),
+            width: 0.0,
+            object_id: 0,
+        };
+        rec.set_face_normal(ray, outward_normal);
+        rec
+    }
+    /// Returns the same record with the given texture coordinates and the derivatives of the
+    /// surface point with respect to them.
+    pub fn with_uv(self, (u, v): (f64, f64), (dpdu, dpdv): (Vec3, Vec3)) -> Self {
+        Self {
+            u,
+            v,
+            dpdu,
+            dpdv,
+            ..self
         }
     }
     /// Used to set the outward normal of the surface.
@@ -32,10 +83,75 @@ impl HitRecord {
             -outward_normal
         };
     }
+    /// Returns an orthonormal tangent and bitangent around the normal, following the direction of
+    /// increasing `u`. Returns None where the surface has no usable parametrisation.
+    pub fn tangent_frame(&self) -> Option<(Vec3, Vec3)> {
+        let tangent = self.dpdu - self.normal * self.normal.dot(&self.dpdu);
+        if near_zero(tangent) {
+            return None;
+        }
+        let tangent = tangent.normalize();
+        Some((tangent, self.normal.cross(&tangent)))
+    }
+}
+
+/// Defines a span of a ray lying inside an object, from the surface it enters through to the one
+/// it leaves through. Spans reaching past either end of the ray start or stop at infinity.
+#[derive(Clone)]
+pub struct Interval {
+    pub enter: HitRecord,
+    pub exit: HitRecord,
 }
 
 /// Defines the interfaces that can be implemented on any kind of object abstraction.
 pub trait Hittable: Sync + Send {
     /// Defines the hit operation on the object with the provided Ray.
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
+
+    /// Returns a box containing the whole object, or None if it is unbounded.
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+
+    /// Returns the spans of the whole line of `ray`, behind its origin included, lying inside the
+    /// object, in increasing order. The default implementation pairs every surface crossed by the
+    /// ray by orientation, which suits closed surfaces that do not intersect themselves.
+    fn intervals(&self, ray: &Ray) -> Vec<Interval> {
+        let mut intervals = vec![];
+        let mut enter: Option<HitRecord> = None;
+        let mut t_min = -INFINITY;
+        // Bounds the number of crossings in case a surface keeps being hit at the same distance.
+        for _ in 0..64 {
+            let rec = match self.hit(ray, t_min, INFINITY) {
+                Some(rec) => rec,
+                None => break,
+            };
+            t_min = rec.t + 1e-9 * rec.t.abs().max(1.0);
+            if rec.front_face {
+                enter.get_or_insert(rec);
+            } else {
+                let enter = enter.take().unwrap_or_else(|| HitRecord {
+                    t: -INFINITY,
+                    ..rec.clone()
+                });
+                intervals.push(Interval { enter, exit: rec });
+            }
+        }
+        if let Some(enter) = enter {
+            let exit = HitRecord {
+                t: INFINITY,
+                ..enter.clone()
+            };
+            intervals.push(Interval { enter, exit });
+        }
+        intervals
+    }
+}
+
+/// Returns the nearest of two optional hits.
+pub fn nearest(a: Option<HitRecord>, b: Option<HitRecord>) -> Option<HitRecord> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if a.t <= b.t { a } else { b }),
+        (a, b) => a.or(b),
+    }
 }
@@ -1,6 +1,11 @@
 use std::sync::Arc;
 
-use crate::{HitRecord, Hittable, Ray};
+use crate::{
+    aabb::Aabb,
+    csg::{combine, CsgOperation},
+    hittable::Interval,
+    HitRecord, Hittable, Ray,
+};
 
 /// Defines a data-structure to store all the Hittable objects.
 pub struct HittableList {
@@ -30,13 +35,39 @@ impl Hittable for HittableList {
         let mut hit_anything = None;
         let mut closest_so_far = t_max;
 
-        for object in self.objects.iter() {
-            if let Some(temp_rec) = object.hit(ray, t_min, closest_so_far) {
+        for (object_id, object) in self.objects.iter().enumerate() {
+            if let Some(mut temp_rec) = object.hit(ray, t_min, closest_so_far) {
                 closest_so_far = temp_rec.t;
+                temp_rec.object_id = object_id;
                 hit_anything = Some(temp_rec);
             }
         }
 
         hit_anything
     }
+
+    /// Provides the spans inside any of the objects, as if they were joined by a union.
+    fn intervals(&self, ray: &Ray) -> Vec<Interval> {
+        self.objects
+            .iter()
+            .enumerate()
+            .fold(vec![], |intervals, (object_id, object)| {
+                let mut spans = object.intervals(ray);
+                for span in &mut spans {
+                    span.enter.object_id = object_id;
+                    span.exit.object_id = object_id;
+                }
+                combine(&intervals, &spans, CsgOperation::Union)
+            })
+    }
+
+    /// Provides the box around every object, if they all have one.
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.objects
+            .iter()
+            .try_fold(None, |bbox: Option<Aabb>, object| {
+                let object = object.bounding_box()?;
+                Some(Some(bbox.map_or(object, |bbox| bbox.surrounding(&object))))
+            })?
+    }
 }
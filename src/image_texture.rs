@@ -0,0 +1,293 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use image::{ColorType, ImageError};
+
+use crate::{texture::Texture, Color, Point3};
+
+/// Defines how the stored values of an image file are to be interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    /// Gamma encoded colours, converted to linear values on load. Floating point formats such as
+    /// HDR are always stored linearly and are left untouched.
+    Srgb,
+    /// Raw data such as normal or height maps, loaded as is.
+    Linear,
+}
+
+/// Defines how texture coordinates outside of [0, 1] are mapped onto the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WrapMode {
+    Repeat,
+    Clamp,
+    Mirror,
+}
+
+impl WrapMode {
+    /// Maps a texel index onto `0..size`.
+    fn wrap(&self, i: i64, size: usize) -> usize {
+        let size = size as i64;
+        let wrapped = match self {
+            WrapMode::Repeat => i.rem_euclid(size),
+            WrapMode::Clamp => i.clamp(0, size - 1),
+            WrapMode::Mirror => {
+                let k = i.rem_euclid(2 * size);
+                if k < size {
+                    k
+                } else {
+                    2 * size - 1 - k
+                }
+            }
+        };
+        wrapped as usize
+    }
+}
+
+/// Defines how texels are combined when looking up a texture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    Nearest,
+    Bilinear,
+}
+
+/// Converts a single gamma encoded sRGB channel to linear.
+pub fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Defines a decoded image, with linear colours stored row by row from the top.
+pub struct ImageData {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl ImageData {
+    /// Wraps already linear pixels, stored row by row from the top.
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> Self {
+        assert_eq!(pixels.len(), width * height);
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Decodes a PNG, JPEG or Radiance HDR file.
+    pub fn open(path: impl AsRef<Path>, color_space: ColorSpace) -> Result<Self, ImageError> {
+        let image = image::open(path)?;
+        let is_float = matches!(image.color(), ColorType::Rgb32F | ColorType::Rgba32F);
+        let convert = |c: f32| match color_space {
+            ColorSpace::Srgb if !is_float => srgb_to_linear(c as f64),
+            _ => c as f64,
+        };
+        let rgb = image.to_rgb32f();
+        let pixels = rgb
+            .pixels()
+            .map(|p| Color::new(convert(p[0]), convert(p[1]), convert(p[2])))
+            .collect();
+
+        Ok(Self::new(
+            rgb.width() as usize,
+            rgb.height() as usize,
+            pixels,
+        ))
+    }
+
+    /// Returns the width of the image in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the image in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the linear colour of the pixel at (`x`, `y`), counted from the top left corner.
+    pub fn texel(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+}
+
+/// Defines a texture looked up from an image, `v` going up from the bottom row.
+#[derive(Clone)]
+pub struct ImageTexture {
+    image: Arc<ImageData>,
+    wrap: WrapMode,
+    filter: Filter,
+}
+
+impl ImageTexture {
+    /// Creates a repeating, bilinearly filtered texture from a decoded image.
+    pub fn new(image: Arc<ImageData>) -> Self {
+        Self {
+            image,
+            wrap: WrapMode::Repeat,
+            filter: Filter::Bilinear,
+        }
+    }
+
+    /// Decodes an image file into a texture, without caching it.
+    pub fn open(path: impl AsRef<Path>, color_space: ColorSpace) -> Result<Self, ImageError> {
+        Ok(Self::new(Arc::new(ImageData::open(path, color_space)?)))
+    }
+
+    /// Sets how coordinates outside of [0, 1] are handled.
+    pub fn with_wrap(mut self, wrap: WrapMode) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    /// Sets how texels are combined.
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    fn texel(&self, x: i64, y: i64) -> Color {
+        self.image.texel(
+            self.wrap.wrap(x, self.image.width),
+            self.wrap.wrap(y, self.image.height),
+        )
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _point: &Point3) -> Color {
+        // Makes empty images stand out instead of panicking.
+        if self.image.width == 0 || self.image.height == 0 {
+            return Color::new(0.0, 1.0, 1.0);
+        }
+        let x = u * self.image.width as f64;
+        let y = (1.0 - v) * self.image.height as f64;
+
+        match self.filter {
+            Filter::Nearest => self.texel(x.floor() as i64, y.floor() as i64),
+            Filter::Bilinear => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (tx, ty) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+
+                let top = (1.0 - tx) * self.texel(x0, y0) + tx * self.texel(x0 + 1, y0);
+                let bottom = (1.0 - tx) * self.texel(x0, y0 + 1) + tx * self.texel(x0 + 1, y0 + 1);
+                (1.0 - ty) * top + ty * bottom
+            }
+        }
+    }
+}
+
+/// Holds a cached image once decoded, locked while it is being decoded.
+type CacheSlot = Arc<Mutex<Option<Arc<ImageData>>>>;
+
+/// Defines a store of decoded images, so that a file shared by many materials is decoded once.
+#[derive(Default)]
+pub struct TextureCache {
+    images: Mutex<HashMap<(PathBuf, ColorSpace), CacheSlot>>,
+}
+
+impl TextureCache {
+    /// Returns the decoded image at `path`, decoding it only on first use.
+    pub fn load(
+        &self,
+        path: impl AsRef<Path>,
+        color_space: ColorSpace,
+    ) -> Result<Arc<ImageData>, ImageError> {
+        let path = path.as_ref();
+        let slot = self
+            .images
+            .lock()
+            .unwrap()
+            .entry((path.to_path_buf(), color_space))
+            .or_default()
+            .clone();
+
+        // Threads wanting an image being decoded wait for it rather than decode it again, while
+        // other images can still be loaded.
+        let mut slot = slot.lock().unwrap();
+        if let Some(image) = slot.as_ref() {
+            return Ok(image.clone());
+        }
+        let image = Arc::new(ImageData::open(path, color_space)?);
+        *slot = Some(image.clone());
+        Ok(image)
+    }
+
+    /// Returns a texture of the image at `path`, sharing its pixels with every other user.
+    pub fn texture(
+        &self,
+        path: impl AsRef<Path>,
+        color_space: ColorSpace,
+    ) -> Result<ImageTexture, ImageError> {
+        Ok(ImageTexture::new(self.load(path, color_space)?))
+    }
+
+    /// Drops every cached image not currently used by a texture.
+    pub fn clear(&self) {
+        self.images.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap_test() {
+        assert_eq!(WrapMode::Repeat.wrap(-1, 4), 3);
+        assert_eq!(WrapMode::Clamp.wrap(-1, 4), 0);
+        assert_eq!(WrapMode::Clamp.wrap(7, 4), 3);
+        assert_eq!(WrapMode::Mirror.wrap(4, 4), 3);
+        assert_eq!(WrapMode::Mirror.wrap(-1, 4), 0);
+    }
+
+    #[test]
+    fn bilinear_test() {
+        let black = Color::new(0.0, 0.0, 0.0);
+        let white = Color::new(1.0, 1.0, 1.0);
+        let image = Arc::new(ImageData::new(2, 1, vec![black, white]));
+        let texture = ImageTexture::new(image).with_wrap(WrapMode::Clamp);
+        let origin = Point3::new(0.0, 0.0, 0.0);
+
+        assert_eq!(texture.value(0.5, 0.5, &origin), 0.5 * white);
+        assert_eq!(texture.value(0.0, 0.5, &origin), black);
+        assert_eq!(texture.value(1.0, 0.5, &origin), white);
+    }
+
+    #[test]
+    fn cache_test() {
+        let path = std::env::temp_dir().join(format!("cache_test_{}.png", std::process::id()));
+        image::RgbImage::from_pixel(4, 4, image::Rgb([255, 128, 0]))
+            .save(&path)
+            .unwrap();
+
+        // Threads asking for the same image at once all get the one decoded copy.
+        let cache = TextureCache::default();
+        let images = std::thread::scope(|scope| {
+            let threads = (0..8)
+                .map(|_| scope.spawn(|| cache.load(&path, ColorSpace::Srgb).unwrap()))
+                .collect::<Vec<_>>();
+            threads
+                .into_iter()
+                .map(|thread| thread.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        assert!(images.iter().all(|image| Arc::ptr_eq(image, &images[0])));
+        let linear = cache.load(&path, ColorSpace::Linear).unwrap();
+        assert!(!Arc::ptr_eq(&linear, &images[0]));
+        assert!((linear.texel(0, 0) - Color::new(1.0, 128.0 / 255.0, 0.0)).norm() < 1e-6);
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(cache.load(&path, ColorSpace::Srgb).is_ok());
+        cache.clear();
+        assert!(cache.load(&path, ColorSpace::Srgb).is_err());
+    }
+}
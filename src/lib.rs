@@ -22,3 +22,61 @@ pub mod camera;
 
 pub mod material;
 use material::{Lambertian, Material};
+
+pub mod spectrum;
+
+pub mod framebuffer;
+
+pub mod renderer;
+
+pub mod texture;
+
+pub mod image_texture;
+
+pub mod perlin;
+
+pub mod pixel_filter;
+
+pub mod sampler;
+
+pub mod aov;
+
+pub mod denoiser;
+
+pub mod exr;
+
+pub mod frame;
+
+pub mod plane;
+
+pub mod cylinder;
+
+pub mod cone;
+
+pub mod torus;
+
+pub mod csg;
+
+pub mod sdf;
+
+pub mod heightfield;
+
+pub mod aabb;
+
+pub mod bvh;
+
+pub mod mesh;
+
+pub mod curve;
+
+pub mod ply;
+
+pub mod point_cloud;
+
+pub mod stl;
+
+pub mod gltf_import;
+
+pub mod transform;
+
+pub mod scene_graph;
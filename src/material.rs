@@ -1,20 +1,39 @@
+use std::sync::Arc;
+
 use crate::{
     definitions::{
-        near_zero, random_double, random_in_unit_sphere, random_unit_vector, reflect, reflectance,
-        refract,
+        degrees_to_radians, near_zero, random_in_unit_sphere, random_unit_vector, reflect,
+        reflectance, refract, sample_1d, sample_2d, PI,
     },
-    Color, HitRecord, Ray,
+    spectrum::Dispersion,
+    texture::{SolidColor, Texture},
+    Color, HitRecord, Ray, Vec3,
 };
 
 pub trait Material: Sync + Send {
     fn scatter(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Option<(Color, Ray)>;
+
+    /// Returns the base colour of the surface at `rec`, as written to the albedo AOV.
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        Color::new(1.0, 1.0, 1.0)
+    }
+
+    /// Returns the light given off by the surface at `rec`, black for all but light sources.
+    fn emitted(&self, _rec: &HitRecord) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
 }
 pub struct Lambertian {
-    albedo: Color,
+    albedo: Arc<dyn Texture>,
 }
 
 impl Lambertian {
     pub fn new(albedo: Color) -> Self {
+        Self::textured(Arc::new(SolidColor::new(albedo)))
+    }
+
+    /// Used to vary the albedo over the surface with a texture.
+    pub fn textured(albedo: Arc<dyn Texture>) -> Self {
         Self { albedo }
     }
 }
@@ -27,7 +46,14 @@ impl Material for Lambertian {
             scatter_direction = rec.normal;
         }
 
-        Some((self.albedo.clone(), Ray::new(rec.point, scatter_direction)))
+        Some((
+            self.albedo.value(rec.u, rec.v, &rec.point),
+            Ray::new(rec.point, scatter_direction),
+        ))
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.albedo.value(rec.u, rec.v, &rec.point)
     }
 }
 
@@ -54,39 +80,705 @@ impl Material for Metal {
             None
         }
     }
+
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        self.albedo
+    }
 }
 
 pub struct Dielectric {
     refractive_index: f64,
+    dispersion: Option<Dispersion>,
+    absorption: Color,
+    roughness: f64,
 }
 
 impl Dielectric {
     pub fn new(refractive_index: f64) -> Self {
-        Self { refractive_index }
+        Self {
+            refractive_index,
+            dispersion: None,
+            absorption: Color::new(0.0, 0.0, 0.0),
+            roughness: 0.0,
+        }
+    }
+
+    /// Makes the index of refraction depend on the wavelength of spectrally traced rays. Rays
+    /// without a wavelength use the index at the d line.
+    pub fn with_dispersion(mut self, dispersion: Dispersion) -> Self {
+        self.refractive_index = dispersion.refractive_index(Dispersion::D_LINE);
+        self.dispersion = Some(dispersion);
+        self
+    }
+
+    /// Returns the index of refraction seen by `ray`.
+    fn refractive_index(&self, ray: &Ray) -> f64 {
+        match (self.dispersion, ray.wavelength()) {
+            (Some(dispersion), Some(lambda)) => dispersion.refractive_index(lambda),
+            _ => self.refractive_index,
+        }
+    }
+
+    /// Sets the per-unit-distance absorption coefficient of the medium, used to attenuate light
+    /// travelling through it following the Beer–Lambert law.
+    pub fn with_absorption(mut self, absorption: Color) -> Self {
+        self.absorption = absorption;
+        self
+    }
+
+    /// Sets the absorption so that light crossing `distance` units of the medium is tinted to
+    /// `color`.
+    ///
+    /// Panics if `distance` is not positive.
+    pub fn with_tint(self, color: Color, distance: f64) -> Self {
+        assert!(distance > 0.0, "tint distance must be positive");
+        let coefficient = |c: f64| -c.clamp(f64::MIN_POSITIVE, 1.0).ln() / distance;
+        self.with_absorption(Color::new(
+            coefficient(color.x),
+            coefficient(color.y),
+            coefficient(color.z),
+        ))
+    }
+
+    /// Sets how much the surface normal is perturbed, to get frosted rather than clear refraction.
+    pub fn with_roughness(mut self, roughness: f64) -> Self {
+        self.roughness = roughness.max(0.0);
+        self
+    }
+
+    /// Returns the fraction of light that survives the path of `r_in` up to `rec`.
+    fn transmittance(&self, r_in: &Ray, rec: &HitRecord) -> Color {
+        // Only rays hitting the surface from the inside have travelled through the medium.
+        if rec.front_face {
+            return Color::new(1.0, 1.0, 1.0);
+        }
+        let distance = rec.t * r_in.direction().norm();
+        self.absorption.map(|a| (-a * distance).exp())
+    }
+
+    /// Returns the normal used for refraction, perturbed by the roughness of the surface.
+    fn microfacet_normal(&self, normal: &Vec3) -> Vec3 {
+        if self.roughness <= 0.0 {
+            return *normal;
+        }
+        let perturbed = normal + self.roughness * random_in_unit_sphere();
+        if near_zero(perturbed) || perturbed.dot(normal) <= 0.0 {
+            *normal
+        } else {
+            perturbed.normalize()
+        }
     }
 }
 
 impl Material for Dielectric {
     fn scatter(&self, r_in: &Ray, rec: &HitRecord, _scattered: &Ray) -> Option<(Color, Ray)> {
+        let refractive_index = self.refractive_index(r_in);
         let refraction_ratio = if rec.front_face {
-            1.0 / self.refractive_index
+            1.0 / refractive_index
         } else {
-            self.refractive_index
+            refractive_index
         };
 
+        let normal = self.microfacet_normal(&rec.normal);
         let unit_direction = r_in.direction().normalize();
-        let cos_theta = -unit_direction.dot(&rec.normal).min(1.0);
+        let cos_theta = -unit_direction.dot(&normal).min(1.0);
         let sin_theta = (1.0 - cos_theta.powi(2)).sqrt();
 
         let cannot_refract = refraction_ratio * sin_theta > 1.0
-            || reflectance(cos_theta, refraction_ratio) > random_double(0.0, 1.0);
+            || reflectance(cos_theta, refraction_ratio) > sample_1d();
 
         let direction = if cannot_refract {
-            reflect(&unit_direction, &rec.normal)
+            reflect(&unit_direction, &normal)
+        } else {
+            refract(&unit_direction, &normal, refraction_ratio)
+        };
+
+        Some((self.transmittance(r_in, rec), Ray::new(rec.point, direction)))
+    }
+}
+
+/// Defines a blend of two materials, picking one of them per scattering event.
+pub struct MixMaterial {
+    first: Arc<dyn Material>,
+    second: Arc<dyn Material>,
+    weight: Arc<dyn Texture>,
+}
+
+impl MixMaterial {
+    /// Blends two materials with a constant `weight`, 0.0 giving only `first` and 1.0 only
+    /// `second`.
+    pub fn new(first: Arc<dyn Material>, second: Arc<dyn Material>, weight: f64) -> Self {
+        Self::textured(
+            first,
+            second,
+            Arc::new(SolidColor::new(Color::new(weight, weight, weight))),
+        )
+    }
+
+    /// Blends two materials with a weight read from the average channel value of a texture.
+    pub fn textured(
+        first: Arc<dyn Material>,
+        second: Arc<dyn Material>,
+        weight: Arc<dyn Texture>,
+    ) -> Self {
+        Self {
+            first,
+            second,
+            weight,
+        }
+    }
+}
+
+impl Material for MixMaterial {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Option<(Color, Ray)> {
+        let weight = self.weight.value(rec.u, rec.v, &rec.point).mean();
+        if sample_1d() < weight {
+            self.second.scatter(r_in, rec, scattered)
+        } else {
+            self.first.scatter(r_in, rec, scattered)
+        }
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        let weight = self.weight.value(rec.u, rec.v, &rec.point).mean();
+        (1.0 - weight) * self.first.albedo(rec) + weight * self.second.albedo(rec)
+    }
+
+    fn emitted(&self, rec: &HitRecord) -> Color {
+        let weight = self.weight.value(rec.u, rec.v, &rec.point).mean();
+        (1.0 - weight) * self.first.emitted(rec) + weight * self.second.emitted(rec)
+    }
+}
+
+/// Defines a clear dielectric coat layered over a base material.
+pub struct Coated {
+    base: Arc<dyn Material>,
+    refractive_index: f64,
+}
+
+impl Coated {
+    pub fn new(base: Arc<dyn Material>, refractive_index: f64) -> Self {
+        Self {
+            base,
+            refractive_index,
+        }
+    }
+}
+
+impl Material for Coated {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Option<(Color, Ray)> {
+        // The coat is only seen from outside, rays leaving the object go straight to the base.
+        if !rec.front_face {
+            return self.base.scatter(r_in, rec, scattered);
+        }
+
+        let unit_direction = r_in.direction().normalize();
+        let cos_theta = -unit_direction.dot(&rec.normal).min(1.0);
+        if reflectance(cos_theta, 1.0 / self.refractive_index) > sample_1d() {
+            Some((
+                Color::new(1.0, 1.0, 1.0),
+                Ray::new(rec.point, reflect(&unit_direction, &rec.normal)),
+            ))
+        } else {
+            self.base.scatter(r_in, rec, scattered)
+        }
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.base.albedo(rec)
+    }
+
+    fn emitted(&self, rec: &HitRecord) -> Color {
+        self.base.emitted(rec)
+    }
+}
+
+/// Returns `perturbed` if it lies on the same side of the surface as the normal of `rec`.
+fn shading_normal(rec: &HitRecord, perturbed: Vec3) -> Vec3 {
+    if near_zero(perturbed) || perturbed.dot(&rec.normal) <= 0.0 {
+        rec.normal
+    } else {
+        perturbed.normalize()
+    }
+}
+
+/// Defines a material whose shading normal is read from a tangent space normal map, encoded with
+/// each channel mapped from [-1, 1] to [0, 1].
+pub struct NormalMapped {
+    base: Arc<dyn Material>,
+    map: Arc<dyn Texture>,
+    strength: f64,
+}
+
+impl NormalMapped {
+    pub fn new(base: Arc<dyn Material>, map: Arc<dyn Texture>, strength: f64) -> Self {
+        Self {
+            base,
+            map,
+            strength,
+        }
+    }
+}
+
+impl Material for NormalMapped {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Option<(Color, Ray)> {
+        let (tangent, bitangent) = match rec.tangent_frame() {
+            Some(frame) => frame,
+            None => return self.base.scatter(r_in, rec, scattered),
+        };
+        let n = self.map.value(rec.u, rec.v, &rec.point) * 2.0 - Vec3::new(1.0, 1.0, 1.0);
+        let perturbed = self.strength * (n.x * tangent + n.y * bitangent) + n.z * rec.normal;
+
+        let mut shaded = rec.clone();
+        shaded.normal = shading_normal(rec, perturbed);
+        self.base.scatter(r_in, &shaded, scattered)
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.base.albedo(rec)
+    }
+
+    fn emitted(&self, rec: &HitRecord) -> Color {
+        self.base.emitted(rec)
+    }
+}
+
+/// Defines a material whose shading normal follows the slope of a height map, read from the
+/// average channel value of a texture and multiplied by `scale`.
+pub struct BumpMapped {
+    base: Arc<dyn Material>,
+    height: Arc<dyn Texture>,
+    scale: f64,
+}
+
+impl BumpMapped {
+    pub fn new(base: Arc<dyn Material>, height: Arc<dyn Texture>, scale: f64) -> Self {
+        Self {
+            base,
+            height,
+            scale,
+        }
+    }
+}
+
+impl Material for BumpMapped {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Option<(Color, Ray)> {
+        let delta = 1e-3;
+        // Solid textures vary with the point rather than (u, v), both are moved together.
+        let height = |du: f64, dv: f64| {
+            let point = rec.point + du * rec.dpdu + dv * rec.dpdv;
+            self.scale * self.height.value(rec.u + du, rec.v + dv, &point).mean()
+        };
+        let h = height(0.0, 0.0);
+        let dhdu = (height(delta, 0.0) - h) / delta;
+        let dhdv = (height(0.0, delta) - h) / delta;
+
+        let dpdu = rec.dpdu + dhdu * rec.normal;
+        let dpdv = rec.dpdv + dhdv * rec.normal;
+        let perturbed = dpdu.cross(&dpdv);
+        // The cross product follows the parametrisation, flip it to the side of the normal.
+        let perturbed = if perturbed.dot(&rec.normal) < 0.0 {
+            -perturbed
         } else {
-            refract(&unit_direction, &rec.normal, refraction_ratio)
+            perturbed
+        };
+
+        let mut shaded = rec.clone();
+        shaded.normal = shading_normal(rec, perturbed);
+        self.base.scatter(r_in, &shaded, scattered)
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.base.albedo(rec)
+    }
+
+    fn emitted(&self, rec: &HitRecord) -> Color {
+        self.base.emitted(rec)
+    }
+}
+
+/// Returns a sample of the standard normal distribution, by the Box–Muller transform.
+fn sample_gaussian() -> f64 {
+    let (a, b) = sample_2d();
+    (-2.0 * (1.0 - a).ln()).sqrt() * (2.0 * PI * b).cos()
+}
+
+/// Defines a hair fibre following a simplified Marschner model: light reflects off the surface of
+/// the fibre (R), goes through it (TT) or reflects once inside it (TRT), each lobe tinted by the
+/// absorption along its path. The fibre runs along the `dpdu` of the hit and is as wide as its
+/// `width`, as set by [curves](crate::curve::Curve). Hits on surfaces without a width do not
+/// scatter.
+pub struct Hair {
+    color: Color,
+    absorption: Color,
+    refractive_index: f64,
+    roughness: f64,
+    scale_angle: f64,
+}
+
+impl Hair {
+    /// Creates a fibre whose multiple scattering gives about `color`, with a `roughness` between
+    /// 0 for smooth and 1 for very rough fibres.
+    pub fn new(color: Color, roughness: f64) -> Self {
+        let roughness = roughness.clamp(0.01, 1.0);
+        // The fit of Chiang et al. from the colour to the absorption per fibre diameter.
+        let fit = 5.969 - 0.215 * roughness + 2.532 * roughness.powi(2) - 10.73 * roughness.powi(3)
+            + 5.574 * roughness.powi(4)
+            + 0.245 * roughness.powi(5);
+        Self {
+            color,
+            absorption: color.map(|c| (c.clamp(1e-4, 1.0).ln() / fit).powi(2)),
+            refractive_index: 1.55,
+            roughness,
+            scale_angle: degrees_to_radians(2.0),
+        }
+    }
+
+    /// Used to set the absorption per fibre diameter directly, instead of from a colour.
+    pub fn with_absorption(mut self, absorption: Color) -> Self {
+        self.absorption = absorption;
+        self
+    }
+
+    pub fn with_refractive_index(mut self, refractive_index: f64) -> Self {
+        self.refractive_index = refractive_index;
+        self
+    }
+
+    /// Used to set the tilt of the cuticle scales in degrees, which shifts the lobes along the
+    /// fibre.
+    pub fn with_scale_angle(mut self, degrees: f64) -> Self {
+        self.scale_angle = degrees_to_radians(degrees);
+        self
+    }
+}
+
+impl Material for Hair {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, _scattered: &Ray) -> Option<(Color, Ray)> {
+        if rec.width <= 0.0 {
+            return None;
+        }
+        let tangent = rec.dpdu.try_normalize(1e-12)?;
+        let normal = (rec.normal - rec.normal.dot(&tangent) * tangent).try_normalize(1e-12)?;
+        let binormal = tangent.cross(&normal);
+        let wi = -r_in.direction().normalize();
+        let sin_theta_i = wi.dot(&tangent).clamp(-1.0, 1.0);
+        let cos_theta_i = (1.0 - sin_theta_i.powi(2)).sqrt().max(1e-6);
+        let phi_i = wi.dot(&binormal).atan2(wi.dot(&normal));
+
+        // Where across the fibre the light enters, and the angles of its path inside.
+        let h = 2.0 * sample_1d() - 1.0;
+        let gamma_i = h.asin();
+        let eta = self.refractive_index;
+        let cos_theta_t = (1.0 - (sin_theta_i / eta).powi(2)).sqrt();
+        let eta_p = (eta * eta - sin_theta_i.powi(2)).sqrt() / cos_theta_i;
+        let gamma_t = (h / eta_p).clamp(-1.0, 1.0).asin();
+        let fresnel = reflectance(cos_theta_i * gamma_i.cos(), eta);
+        let transmittance = self
+            .absorption
+            .map(|a| (-a * 2.0 * gamma_t.cos() / cos_theta_t).exp());
+
+        // Picks one of the R, TT and TRT lobes in proportion to the light it carries.
+        let lobes = [
+            Color::new(fresnel, fresnel, fresnel),
+            (1.0 - fresnel).powi(2) * transmittance,
+            (1.0 - fresnel).powi(2) * fresnel * transmittance.component_mul(&transmittance),
+        ];
+        let weights = lobes.map(|lobe| lobe.mean());
+        let total = weights.iter().sum::<f64>();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut pick = sample_1d() * total;
+        let lobe = (0..2)
+            .find(|&lobe| {
+                pick -= weights[lobe];
+                pick < 0.0
+            })
+            .unwrap_or(2);
+
+        let shift = [2.0, -1.0, -4.0][lobe] * self.scale_angle;
+        let width = [1.0, 0.5, 2.0][lobe] * self.roughness * PI / 4.0;
+        let theta_o = (-sin_theta_i.asin() + shift + width * sample_gaussian())
+            .clamp(-PI / 2.0 + 1e-4, PI / 2.0 - 1e-4);
+        let p = lobe as f64;
+        let phi_o = phi_i + 2.0 * p * gamma_t - 2.0 * gamma_i
+            + p * PI
+            + self.roughness * PI * sample_gaussian();
+        let direction = theta_o.sin() * tangent
+            + theta_o.cos() * (phi_o.cos() * normal + phi_o.sin() * binormal);
+
+        // The path through the fibre is already accounted for, the ray leaves from beyond it so
+        // as not to hit it again.
+        let origin = rec.point + rec.width * direction;
+        Some((
+            lobes[lobe] * (total / weights[lobe]),
+            Ray::new(origin, direction),
+        ))
+    }
+
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        self.color
+    }
+}
+
+/// Defines a light source: a surface giving off light of the colour of a texture on both of its
+/// sides, without scattering any.
+pub struct DiffuseLight {
+    emit: Arc<dyn Texture>,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Color) -> Self {
+        Self::textured(Arc::new(SolidColor::new(emit)))
+    }
+
+    /// Used to vary the emitted light over the surface with a texture.
+    pub fn textured(emit: Arc<dyn Texture>) -> Self {
+        Self { emit }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> Option<(Color, Ray)> {
+        None
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.emit.value(rec.u, rec.v, &rec.point)
+    }
+
+    fn emitted(&self, rec: &HitRecord) -> Color {
+        self.emit.value(rec.u, rec.v, &rec.point)
+    }
+}
+
+/// Defines a surface that gives off light of the colour of a texture on top of scattering like
+/// its base material.
+pub struct Emissive {
+    base: Arc<dyn Material>,
+    emit: Arc<dyn Texture>,
+}
+
+impl Emissive {
+    pub fn new(base: Arc<dyn Material>, emit: Arc<dyn Texture>) -> Self {
+        Self { base, emit }
+    }
+}
+
+impl Material for Emissive {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Option<(Color, Ray)> {
+        self.base.scatter(r_in, rec, scattered)
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.base.albedo(rec)
+    }
+
+    fn emitted(&self, rec: &HitRecord) -> Color {
+        self.base.emitted(rec) + self.emit.value(rec.u, rec.v, &rec.point)
+    }
+}
+
+/// Defines a material tinted by the colour of the surface at each hit, such as the colours
+/// interpolated across a mesh from its vertices. The light it gives off is left as is.
+pub struct VertexTinted {
+    base: Arc<dyn Material>,
+}
+
+impl VertexTinted {
+    pub fn new(base: Arc<dyn Material>) -> Self {
+        Self { base }
+    }
+}
+
+impl Material for VertexTinted {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Option<(Color, Ray)> {
+        self.base
+            .scatter(r_in, rec, scattered)
+            .map(|(attenuation, ray)| (attenuation.component_mul(&rec.color), ray))
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.base.albedo(rec).component_mul(&rec.color)
+    }
+
+    fn emitted(&self, rec: &HitRecord) -> Color {
+        self.base.emitted(rec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{curve::Curve, definitions::seed_random, sphere::Sphere, Hittable, Point3};
+
+    /// Returns the direction of the first ray scattered by `material` that goes through the
+    /// surface.
+    fn refracted(material: &dyn Material, ray: &Ray, rec: &HitRecord) -> Vec3 {
+        (0..100)
+            .map(|_| material.scatter(ray, rec, ray).unwrap().1.direction())
+            .find(|direction| direction.dot(&rec.normal) < 0.0)
+            .unwrap()
+            .normalize()
+    }
+
+    #[test]
+    fn dielectric_test() {
+        seed_random(7);
+        let glass: Arc<dyn Material> =
+            Arc::new(Dielectric::new(1.5).with_absorption(Color::new(0.5, 0.0, 1.0)));
+        let ray = Ray::new(Point3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+
+        // Light is only absorbed along the path it travelled inside.
+        let entering = HitRecord::new(&ray, 1.0, &Vec3::new(0.0, 0.0, 1.0), glass.clone());
+        let (attenuation, _) = glass.scatter(&ray, &entering, &ray).unwrap();
+        assert_eq!(attenuation, Color::new(1.0, 1.0, 1.0));
+        let leaving = HitRecord::new(&ray, 2.0, &Vec3::new(0.0, 0.0, -1.0), glass.clone());
+        let (attenuation, _) = glass.scatter(&ray, &leaving, &ray).unwrap();
+        let expected = Color::new((-1.0f64).exp(), 1.0, (-2.0f64).exp());
+        assert!((attenuation - expected).norm() < 1e-12);
+
+        let tinted = Dielectric::new(1.5).with_tint(Color::new(0.8, 0.5, 0.2), 3.0);
+        let leaving = HitRecord::new(&ray, 3.0, &Vec3::new(0.0, 0.0, -1.0), glass.clone());
+        let (attenuation, _) = tinted.scatter(&ray, &leaving, &ray).unwrap();
+        assert!((attenuation - Color::new(0.8, 0.5, 0.2)).norm() < 1e-12);
+
+        // Smooth glass lets light through a face it hits square on without bending it, rough glass
+        // spreads it.
+        let straight = Vec3::new(0.0, 0.0, -1.0);
+        assert_eq!(refracted(glass.as_ref(), &ray, &entering), straight);
+        let frosted = Dielectric::new(1.5).with_roughness(0.5);
+        let spread = (0..20)
+            .map(|_| refracted(&frosted, &ray, &entering))
+            .filter(|direction| (direction - straight).norm() > 1e-3)
+            .count();
+        assert!(spread > 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "tint distance must be positive")]
+    fn tint_distance_test() {
+        Dielectric::new(1.5).with_tint(Color::new(0.8, 0.5, 0.2), 0.0);
+    }
+
+    #[test]
+    fn dispersion_test() {
+        // Blue light bends more than red, rays without a wavelength bend as at the d line.
+        let crown: Arc<dyn Material> =
+            Arc::new(Dielectric::new(1.5).with_dispersion(Dispersion::BK7));
+        let ray = Ray::new(Point3::new(-1.0, 0.0, 1.0), Vec3::new(1.0, 0.0, -1.0));
+        let rec = HitRecord::new(&ray, 1.0, &Vec3::new(0.0, 0.0, 1.0), crown.clone());
+        let sine = |wavelength: Option<f64>| {
+            refracted(crown.as_ref(), &ray.with_wavelength(wavelength), &rec).x
+        };
+        assert!(sine(Some(450.0)) < sine(Some(650.0)));
+        let d_line = Dispersion::BK7.refractive_index(Dispersion::D_LINE);
+        assert!((sine(None) - 0.5f64.sqrt() / d_line).abs() < 1e-9);
+    }
+
+    #[test]
+    fn layered_test() {
+        seed_random(7);
+        let red = Color::new(0.9, 0.1, 0.1);
+        let blue = Color::new(0.1, 0.1, 0.9);
+        let diffuse_red: Arc<dyn Material> = Arc::new(Lambertian::new(red));
+        let diffuse_blue: Arc<dyn Material> = Arc::new(Lambertian::new(blue));
+        let ray = Ray::new(Point3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = HitRecord::new(&ray, 1.0, &Vec3::new(0.0, 0.0, 1.0), diffuse_red.clone());
+        let count = |material: &dyn Material, rec: &HitRecord, color: Color| {
+            (0..2000)
+                .filter(|_| material.scatter(&ray, rec, &ray).unwrap().0 == color)
+                .count()
+        };
+
+        // The weight is the share of scattering events going to the second material.
+        for (weight, expected) in [(0.0, 0), (0.25, 500), (1.0, 2000)] {
+            let mix = MixMaterial::new(diffuse_red.clone(), diffuse_blue.clone(), weight);
+            let blues = count(&mix, &rec, blue) as i64;
+            assert!((blues - expected).abs() < 60);
+            let albedo = (1.0 - weight) * red + weight * blue;
+            assert!((mix.albedo(&rec) - albedo).norm() < 1e-12);
+        }
+
+        // Seen square on, about 4% of the light reflects off a coat of index 1.5, untinted and
+        // mirrored. Rays from the inside go straight to the base.
+        let coated = Coated::new(diffuse_red, 1.5);
+        let white = Color::new(1.0, 1.0, 1.0);
+        let reflected = count(&coated, &rec, white);
+        assert!((50..110).contains(&reflected));
+        let (_, mirrored) = (0..100)
+            .map(|_| coated.scatter(&ray, &rec, &ray).unwrap())
+            .find(|(attenuation, _)| *attenuation == white)
+            .unwrap();
+        assert_eq!(mirrored.direction(), Vec3::new(0.0, 0.0, 1.0));
+        let inside = HitRecord::new(&ray, 1.0, &Vec3::new(0.0, 0.0, -1.0), coated.base.clone());
+        assert_eq!(count(&coated, &inside, red), 2000);
+        assert_eq!(coated.albedo(&rec), red);
+    }
+
+    /// Defines a solid texture getting brighter along x.
+    struct Ramp;
+
+    impl Texture for Ramp {
+        fn value(&self, _u: f64, _v: f64, point: &Point3) -> Color {
+            Color::repeat(point.x)
+        }
+    }
+
+    #[test]
+    fn bump_test() {
+        let mirror: Arc<dyn Material> = Arc::new(Metal::new(Color::new(1.0, 1.0, 1.0), 0.0));
+        let bumped = BumpMapped::new(mirror.clone(), Arc::new(Ramp), 0.5);
+        let ray = Ray::new(Point3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = HitRecord::new(&ray, 1.0, &Vec3::new(0.0, 0.0, 1.0), mirror).with_uv(
+            (0.3, 0.6),
+            (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
+        );
+
+        // The height rises by 0.5 per unit of x, tilting the normal to (-0.5, 0, 1).
+        let (_, reflected) = bumped
+            .scatter(&ray, &rec, &Ray::new(rec.point, rec.normal))
+            .unwrap();
+        assert!((reflected.direction() - Vec3::new(-0.8, 0.0, 0.6)).norm() < 1e-6);
+    }
+
+    #[test]
+    fn hair_test() {
+        seed_random(7);
+        let fibre = |hair: Hair| {
+            let points = [-1.0, 0.0, 1.0, 2.0].map(|x| Point3::new(x, 0.0, 0.0));
+            Curve::new(points, (0.1, 0.1), Arc::new(hair))
+        };
+        let ray = Ray::new(Point3::new(0.3, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let brightness = |color: Color| {
+            let curve = fibre(Hair::new(color, 0.3));
+            let rec = curve.hit(&ray, 0.001, f64::MAX).unwrap();
+            let (mut total, mut through) = (0.0, 0);
+            for _ in 0..1000 {
+                let (attenuation, scattered) = rec.material.scatter(&ray, &rec, &ray).unwrap();
+                assert!(attenuation.iter().all(|&c| (0.0..=1.0).contains(&c)));
+                // Rays leave the fibre without hitting it again.
+                assert!(curve.hit(&scattered, 0.001, f64::MAX).is_none());
+                total += attenuation.mean();
+                through += (scattered.direction().z < 0.0) as u32;
+            }
+            (total / 1000.0, through)
         };
 
-        Some((Color::new(1.0, 1.0, 1.0), Ray::new(rec.point, direction)))
+        let (light, through) = brightness(Color::new(0.9, 0.9, 0.9));
+        let (dark, _) = brightness(Color::new(0.1, 0.1, 0.1));
+        assert!(dark < light);
+        // Light fibres let most of the light through rather than reflecting it.
+        assert!((500..1000).contains(&through));
+
+        // Surfaces have no fibre width to leave through.
+        let sphere = Sphere::new(
+            Point3::new(0.3, 0.0, 0.0),
+            1.0,
+            Arc::new(Hair::new(Color::new(0.9, 0.9, 0.9), 0.3)),
+        );
+        let rec = sphere.hit(&ray, 0.001, f64::MAX).unwrap();
+        assert!(rec.material.scatter(&ray, &rec, &ray).is_none());
     }
 }
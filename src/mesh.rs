@@ -0,0 +1,656 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    aabb::Aabb,
+    bvh::Bvh,
+    color::luminance,
+    frame::Frame,
+    heightfield::intersect_triangle,
+    ply::{Ply, PlyError},
+    stl::{read_stl, StlError},
+    texture::Texture,
+    Color, HitRecord, Hittable, Material, Point3, Ray, Vec3,
+};
+
+/// Defines what can go wrong reading a mesh from a file.
+#[derive(Debug)]
+pub enum MeshError {
+    Ply(PlyError),
+    Stl(StlError),
+    /// The extension of the file is not one of a format that can be read.
+    UnknownFormat(PathBuf),
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MeshError::Ply(error) => write!(f, "{}", error),
+            MeshError::Stl(error) => write!(f, "{}", error),
+            MeshError::UnknownFormat(path) => {
+                write!(f, "unknown mesh format for {}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for MeshError {}
+
+impl From<PlyError> for MeshError {
+    fn from(error: PlyError) -> Self {
+        MeshError::Ply(error)
+    }
+}
+
+impl From<StlError> for MeshError {
+    fn from(error: StlError) -> Self {
+        MeshError::Stl(error)
+    }
+}
+
+/// Weights of the points a new point is averaged from, by index.
+type Stencil = Vec<(usize, f64)>;
+
+/// Defines one step of subdivision: how every new point is averaged from the old ones, and the
+/// faces joining them.
+struct Refinement {
+    /// Points built first, then appended after the old points for `points` to be built from.
+    centroids: Vec<Stencil>,
+    points: Vec<Stencil>,
+    faces: Vec<Vec<usize>>,
+}
+
+/// Defines the edges of a mesh, in the order they are first met going through the faces.
+struct Edges {
+    index: HashMap<(usize, usize), usize>,
+    ends: Vec<(usize, usize)>,
+    /// Faces on either side of every edge, a single one on boundaries.
+    faces: Vec<Vec<usize>>,
+}
+
+impl Edges {
+    fn new(faces: &[Vec<usize>]) -> Self {
+        let mut edges = Self {
+            index: HashMap::new(),
+            ends: vec![],
+            faces: vec![],
+        };
+        for (f, face) in faces.iter().enumerate() {
+            for (k, &a) in face.iter().enumerate() {
+                let b = face[(k + 1) % face.len()];
+                let next = edges.ends.len();
+                let e = *edges.index.entry((a.min(b), a.max(b))).or_insert(next);
+                if e == next {
+                    edges.ends.push((a, b));
+                    edges.faces.push(vec![]);
+                }
+                edges.faces[e].push(f);
+            }
+        }
+        edges
+    }
+
+    fn get(&self, a: usize, b: usize) -> usize {
+        self.index[&(a.min(b), a.max(b))]
+    }
+
+    fn is_boundary(&self, e: usize) -> bool {
+        self.faces[e].len() != 2
+    }
+
+    /// Returns the edges around every one of `n` vertices.
+    fn around(&self, n: usize) -> Vec<Vec<usize>> {
+        let mut around = vec![vec![]; n];
+        for (e, &(a, b)) in self.ends.iter().enumerate() {
+            around[a].push(e);
+            around[b].push(e);
+        }
+        around
+    }
+
+    /// Returns the vertex at the other end of edge `e` from `v`.
+    fn other(&self, e: usize, v: usize) -> usize {
+        let (a, b) = self.ends[e];
+        if a == v {
+            b
+        } else {
+            a
+        }
+    }
+
+    /// Returns the stencil shared by the subdivision schemes for vertices on a boundary, or None
+    /// for vertices inside the surface.
+    fn boundary_stencil(&self, v: usize, around: &[usize]) -> Option<Stencil> {
+        let boundary = around
+            .iter()
+            .filter(|&&e| self.is_boundary(e))
+            .collect::<Vec<_>>();
+        match boundary.len() {
+            0 => None,
+            2 => Some(vec![
+                (v, 0.75),
+                (self.other(*boundary[0], v), 0.125),
+                (self.other(*boundary[1], v), 0.125),
+            ]),
+            // Corners where more than two boundaries meet stay in place.
+            _ => Some(vec![(v, 1.0)]),
+        }
+    }
+}
+
+/// Returns the weighted sum of the `points` in `stencil`.
+fn blend(points: &[Vec3], stencil: &[(usize, f64)]) -> Vec3 {
+    stencil.iter().map(|&(i, w)| w * points[i]).sum()
+}
+
+/// Defines a polygon mesh: shared vertex positions, with optional normals, texture coordinates and
+/// colours per vertex, and faces listing their vertices counterclockwise seen from the outside.
+#[derive(Debug, Clone, Default)]
+pub struct Mesh {
+    pub positions: Vec<Point3>,
+    /// Either empty or one per position.
+    pub normals: Vec<Vec3>,
+    /// Either empty or one per position.
+    pub uvs: Vec<(f64, f64)>,
+    /// Either empty or one per position.
+    pub colors: Vec<Color>,
+    pub faces: Vec<Vec<usize>>,
+}
+
+impl Mesh {
+    pub fn new(positions: Vec<Point3>, faces: Vec<Vec<usize>>) -> Self {
+        Self {
+            positions,
+            normals: vec![],
+            uvs: vec![],
+            colors: vec![],
+            faces,
+        }
+    }
+
+    /// Reads a PLY or STL file, chosen by the extension of `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, MeshError> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("ply") => Ok(Ply::open(path)?.to_mesh()?),
+            Some("stl") => Ok(read_stl(BufReader::new(
+                File::open(path).map_err(StlError::Io)?,
+            ))?),
+            _ => Err(MeshError::UnknownFormat(path.to_path_buf())),
+        }
+    }
+
+    pub fn with_normals(mut self, normals: Vec<Vec3>) -> Self {
+        self.normals = normals;
+        self
+    }
+
+    pub fn with_uvs(mut self, uvs: Vec<(f64, f64)>) -> Self {
+        self.uvs = uvs;
+        self
+    }
+
+    pub fn with_colors(mut self, colors: Vec<Color>) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    /// Used to give every vertex the average normal of the faces around it, weighted by their
+    /// areas.
+    pub fn with_smooth_normals(mut self) -> Self {
+        let mut normals = vec![Vec3::zeros(); self.positions.len()];
+        for face in &self.faces {
+            // Newell's method, whose length is twice the area of the face.
+            let normal = face
+                .iter()
+                .enumerate()
+                .map(|(k, &a)| {
+                    let b = face[(k + 1) % face.len()];
+                    self.positions[a].cross(&self.positions[b])
+                })
+                .sum::<Vec3>();
+            for &v in face {
+                normals[v] += normal;
+            }
+        }
+        self.normals = normals
+            .into_iter()
+            .map(|n| n.try_normalize(0.0).unwrap_or_else(Vec3::zeros))
+            .collect();
+        self
+    }
+
+    /// Returns the same mesh with every face split into a fan of triangles.
+    pub fn triangulate(&self) -> Mesh {
+        let faces = self
+            .faces
+            .iter()
+            .flat_map(|face| {
+                (1..face.len().saturating_sub(1)).map(move |k| vec![face[0], face[k], face[k + 1]])
+            })
+            .collect();
+        Mesh {
+            faces,
+            ..self.clone()
+        }
+    }
+
+    /// Returns the mesh refined `levels` times by Loop subdivision, which splits every triangle in
+    /// four and converges to a smooth surface. Other faces are triangulated first.
+    pub fn loop_subdivide(&self, levels: usize) -> Mesh {
+        (0..levels).fold(self.triangulate(), |mesh, _| mesh.refine(mesh.loop_step()))
+    }
+
+    /// Returns the mesh refined `levels` times by Catmull–Clark subdivision, which splits every
+    /// face into quads and converges to a smooth surface.
+    pub fn catmull_clark(&self, levels: usize) -> Mesh {
+        (0..levels).fold(self.clone(), |mesh, _| {
+            mesh.refine(mesh.catmull_clark_step())
+        })
+    }
+
+    /// Returns the mesh with every vertex moved along its normal by `scale` times the luminance
+    /// of `texture` at that vertex.
+    pub fn displace(&self, texture: &dyn Texture, scale: f64) -> Mesh {
+        let normals = if self.normals.len() == self.positions.len() {
+            self.normals.clone()
+        } else {
+            self.clone().with_smooth_normals().normals
+        };
+        let positions = self
+            .positions
+            .iter()
+            .zip(&normals)
+            .enumerate()
+            .map(|(i, (p, n))| {
+                let (u, v) = self.uvs.get(i).copied().unwrap_or((0.0, 0.0));
+                p + scale * luminance(&texture.value(u, v, p)) * n
+            })
+            .collect();
+        Mesh {
+            positions,
+            ..self.clone()
+        }
+        .with_smooth_normals()
+    }
+
+    /// Returns every face of the mesh as a [Hittable] triangle, ready to go into a [Bvh]. The
+    /// colours of the vertices are interpolated across the faces into the colour of their hits.
+    pub fn to_triangles(&self, material: Arc<dyn Material>) -> Vec<Arc<dyn Hittable>> {
+        let mesh = Arc::new(self.triangulate());
+        (0..mesh.faces.len())
+            .map(|face| {
+                Arc::new(Triangle {
+                    mesh: mesh.clone(),
+                    face,
+                    material: material.clone(),
+                }) as Arc<dyn Hittable>
+            })
+            .collect()
+    }
+
+    /// Returns the triangles of the mesh in a bounding volume hierarchy.
+    pub fn to_bvh(&self, material: Arc<dyn Material>) -> Bvh {
+        Bvh::new(self.to_triangles(material))
+    }
+
+    /// Returns the mesh made by a step of subdivision, with the texture coordinates and colours
+    /// refined like the positions and normals recomputed.
+    fn refine(&self, refinement: Refinement) -> Mesh {
+        let apply = |points: &[Vec3]| {
+            let mut extended = points.to_vec();
+            extended.extend(refinement.centroids.iter().map(|s| blend(points, s)));
+            refinement
+                .points
+                .iter()
+                .map(|s| blend(&extended, s))
+                .collect::<Vec<Vec3>>()
+        };
+        let uvs = if self.uvs.len() == self.positions.len() {
+            let uvs = self
+                .uvs
+                .iter()
+                .map(|&(u, v)| Vec3::new(u, v, 0.0))
+                .collect::<Vec<_>>();
+            apply(&uvs).into_iter().map(|uv| (uv.x, uv.y)).collect()
+        } else {
+            vec![]
+        };
+        let colors = if self.colors.len() == self.positions.len() {
+            apply(&self.colors)
+        } else {
+            vec![]
+        };
+        Mesh {
+            positions: apply(&self.positions),
+            normals: vec![],
+            uvs,
+            colors,
+            faces: refinement.faces,
+        }
+        .with_smooth_normals()
+    }
+
+    /// Returns a step of Loop subdivision of a triangle mesh: old vertices first, then one new
+    /// vertex per edge.
+    fn loop_step(&self) -> Refinement {
+        let n = self.positions.len();
+        let edges = Edges::new(&self.faces);
+        let around = edges.around(n);
+
+        let vertices = around.iter().enumerate().map(|(v, around)| {
+            if around.is_empty() {
+                return vec![(v, 1.0)];
+            }
+            edges.boundary_stencil(v, around).unwrap_or_else(|| {
+                let valence = around.len();
+                let beta = if valence == 3 {
+                    3.0 / 16.0
+                } else {
+                    3.0 / (8.0 * valence as f64)
+                };
+                let mut stencil = vec![(v, 1.0 - valence as f64 * beta)];
+                stencil.extend(around.iter().map(|&e| (edges.other(e, v), beta)));
+                stencil
+            })
+        });
+        let edge_points = edges.ends.iter().enumerate().map(|(e, &(a, b))| {
+            if edges.is_boundary(e) {
+                return vec![(a, 0.5), (b, 0.5)];
+            }
+            let mut stencil = vec![(a, 0.375), (b, 0.375)];
+            stencil.extend(edges.faces[e].iter().map(|&f| {
+                let opposite = self.faces[f].iter().find(|&&v| v != a && v != b);
+                (*opposite.unwrap(), 0.125)
+            }));
+            stencil
+        });
+
+        let faces = self
+            .faces
+            .iter()
+            .flat_map(|face| {
+                let [a, b, c] = [face[0], face[1], face[2]];
+                let [ab, bc, ca] = [(a, b), (b, c), (c, a)].map(|(p, q)| n + edges.get(p, q));
+                [
+                    vec![a, ab, ca],
+                    vec![ab, b, bc],
+                    vec![ca, bc, c],
+                    vec![ab, bc, ca],
+                ]
+            })
+            .collect();
+        Refinement {
+            centroids: vec![],
+            points: vertices.chain(edge_points).collect(),
+            faces,
+        }
+    }
+
+    /// Returns a step of Catmull–Clark subdivision: old vertices first, then one new vertex per
+    /// face and one per edge.
+    fn catmull_clark_step(&self) -> Refinement {
+        let n = self.positions.len();
+        let edges = Edges::new(&self.faces);
+        let around = edges.around(n);
+        let mut faces_around = vec![vec![]; n];
+        for (f, face) in self.faces.iter().enumerate() {
+            for &v in face {
+                faces_around[v].push(f);
+            }
+        }
+
+        let centroids = self
+            .faces
+            .iter()
+            .map(|face| face.iter().map(|&v| (v, 1.0 / face.len() as f64)).collect())
+            .collect::<Vec<Stencil>>();
+        let vertices = around.iter().enumerate().map(|(v, around)| {
+            if around.is_empty() {
+                return vec![(v, 1.0)];
+            }
+            edges.boundary_stencil(v, around).unwrap_or_else(|| {
+                // (F + 2R + (k - 3) P) / k, F averaging the face points around the vertex and R
+                // the midpoints of its edges.
+                let k = around.len() as f64;
+                let faces = &faces_around[v];
+                let mut stencil = vec![(v, (k - 2.0) / k)];
+                stencil.extend(around.iter().map(|&e| (edges.other(e, v), 1.0 / (k * k))));
+                stencil.extend(
+                    faces
+                        .iter()
+                        .map(|&f| (n + f, 1.0 / (k * faces.len() as f64))),
+                );
+                stencil
+            })
+        });
+        let face_points = (0..self.faces.len()).map(|f| vec![(n + f, 1.0)]);
+        let edge_points = edges.ends.iter().enumerate().map(|(e, &(a, b))| {
+            if edges.is_boundary(e) {
+                return vec![(a, 0.5), (b, 0.5)];
+            }
+            let mut stencil = vec![(a, 0.25), (b, 0.25)];
+            stencil.extend(edges.faces[e].iter().map(|&f| (n + f, 0.25)));
+            stencil
+        });
+
+        let edge_start = n + self.faces.len();
+        let faces = self
+            .faces
+            .iter()
+            .enumerate()
+            .flat_map(|(f, face)| {
+                let edges = &edges;
+                let m = face.len();
+                (0..m).map(move |k| {
+                    let (previous, v, next) = (face[(k + m - 1) % m], face[k], face[(k + 1) % m]);
+                    vec![
+                        v,
+                        edge_start + edges.get(v, next),
+                        n + f,
+                        edge_start + edges.get(previous, v),
+                    ]
+                })
+            })
+            .collect();
+        Refinement {
+            centroids,
+            points: vertices.chain(face_points).chain(edge_points).collect(),
+            faces,
+        }
+    }
+}
+
+/// Defines a single triangle of a [Mesh], with normals and texture coordinates interpolated from
+/// its vertices when the mesh has them.
+pub struct Triangle {
+    mesh: Arc<Mesh>,
+    face: usize,
+    material: Arc<dyn Material>,
+}
+
+impl Triangle {
+    fn vertices(&self) -> [usize; 3] {
+        let face = &self.mesh.faces[self.face];
+        [face[0], face[1], face[2]]
+    }
+}
+
+impl Hittable for Triangle {
+    /// Provides a definition of hit() for mesh triangles.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let vertices = self.vertices();
+        let [p0, p1, p2] = vertices.map(|i| self.mesh.positions[i]);
+        let (t, b1, b2) = intersect_triangle(ray, &p0, &p1, &p2)?;
+        if t < t_min || t > t_max {
+            return None;
+        }
+        let b0 = 1.0 - b1 - b2;
+
+        let geometric_normal = (p1 - p0).cross(&(p2 - p0)).normalize();
+        let mut rec = HitRecord::new(ray, t, &geometric_normal, self.material.clone());
+        if self.mesh.normals.len() == self.mesh.positions.len() {
+            let [n0, n1, n2] = vertices.map(|i| self.mesh.normals[i]);
+            if let Some(shading_normal) = (b0 * n0 + b1 * n1 + b2 * n2).try_normalize(1e-12) {
+                rec.normal = if rec.front_face {
+                    shading_normal
+                } else {
+                    -shading_normal
+                };
+            }
+        }
+        if self.mesh.colors.len() == self.mesh.positions.len() {
+            let [c0, c1, c2] = vertices.map(|i| self.mesh.colors[i]);
+            rec.color = b0 * c0 + b1 * c1 + b2 * c2;
+        }
+
+        let [uv0, uv1, uv2] = if self.mesh.uvs.len() != self.mesh.positions.len() {
+            [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]
+        } else {
+            vertices.map(|i| self.mesh.uvs[i])
+        };
+        let uv = (
+            b0 * uv0.0 + b1 * uv1.0 + b2 * uv2.0,
+            b0 * uv0.1 + b1 * uv1.1 + b2 * uv2.1,
+        );
+        // Solves for the derivatives along u and v from the edges, following Pharr et al.
+        let (du02, dv02, du12, dv12) = (uv0.0 - uv2.0, uv0.1 - uv2.1, uv1.0 - uv2.0, uv1.1 - uv2.1);
+        let (dp02, dp12) = (p0 - p2, p1 - p2);
+        let det = du02 * dv12 - dv02 * du12;
+        let derivatives = if det.abs() < 1e-12 {
+            let frame = Frame::new(p0, geometric_normal);
+            (frame.tangent, frame.bitangent)
+        } else {
+            (
+                (dv12 * dp02 - dv02 * dp12) / det,
+                (du02 * dp12 - du12 * dp02) / det,
+            )
+        };
+        Some(rec.with_uv(uv, derivatives))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Aabb::from_points(self.vertices().map(|i| self.mesh.positions[i]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{material::Lambertian, texture::SolidColor};
+
+    fn cube() -> Mesh {
+        let positions = (0..8)
+            .map(|i| {
+                let corner = |bit: usize| if i & bit == 0 { -1.0 } else { 1.0 };
+                Point3::new(corner(1), corner(2), corner(4))
+            })
+            .collect();
+        let faces = vec![
+            vec![0, 4, 6, 2],
+            vec![1, 3, 7, 5],
+            vec![0, 1, 5, 4],
+            vec![2, 6, 7, 3],
+            vec![0, 2, 3, 1],
+            vec![4, 5, 7, 6],
+        ];
+        Mesh::new(positions, faces)
+    }
+
+    #[test]
+    fn subdivision_test() {
+        let smooth = cube().catmull_clark(1);
+        assert_eq!(smooth.positions.len(), 8 + 6 + 12);
+        assert_eq!(smooth.faces.len(), 24);
+        // Corners are pulled in to (Q + 2R) / 3, from the face points and edge midpoints.
+        let corner = Vec3::new(5.0, 5.0, 5.0) / 9.0;
+        assert!((smooth.positions[7] - corner).norm() < 1e-12);
+        assert!(smooth.normals[7].dot(&corner) > 0.0);
+
+        let smooth = cube().loop_subdivide(2);
+        assert_eq!(smooth.faces.len(), 12 * 16);
+        assert!(smooth.positions.iter().all(|p| p.norm() < 3f64.sqrt()));
+
+        // A lone triangle only has boundaries, which stay straight.
+        let triangle = Mesh::new(
+            vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+            ],
+            vec![vec![0, 1, 2]],
+        )
+        .loop_subdivide(1);
+        assert_eq!(triangle.positions[3], Point3::new(0.5, 0.0, 0.0));
+        assert_eq!(triangle.positions[0], Point3::new(0.125, 0.125, 0.0));
+    }
+
+    #[test]
+    fn refine_test() {
+        // Texture coordinates and colours follow the same stencils as the positions, so values
+        // that are an affine function of the position stay so.
+        let cube = cube();
+        let uvs = cube.positions.iter().map(|p| (p.x, p.y)).collect();
+        let colors = cube
+            .positions
+            .iter()
+            .map(|p| (p + Vec3::new(1.0, 1.0, 1.0)) / 2.0)
+            .collect();
+        let smooth = cube.with_uvs(uvs).with_colors(colors).catmull_clark(2);
+        assert_eq!(smooth.uvs.len(), smooth.positions.len());
+        assert_eq!(smooth.colors.len(), smooth.positions.len());
+        for ((p, uv), color) in smooth.positions.iter().zip(&smooth.uvs).zip(&smooth.colors) {
+            assert!((uv.0 - p.x).abs() < 1e-12 && (uv.1 - p.y).abs() < 1e-12);
+            assert!((color - (p + Vec3::new(1.0, 1.0, 1.0)) / 2.0).norm() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn displace_test() {
+        let smooth = cube().catmull_clark(2);
+        let displaced = smooth.displace(&SolidColor::new(Color::new(1.0, 1.0, 1.0)), 0.25);
+        assert_eq!(displaced.faces, smooth.faces);
+        for ((p, n), displaced) in smooth
+            .positions
+            .iter()
+            .zip(&smooth.normals)
+            .zip(&displaced.positions)
+        {
+            // Every vertex moves a quarter along its normal, which points away from the centre.
+            assert!((displaced - (p + 0.25 * n)).norm() < 1e-12);
+            assert!(n.dot(p) > 0.0);
+        }
+    }
+
+    #[test]
+    fn triangles_test() {
+        let material = Arc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
+        let bvh = cube().to_bvh(material);
+        let ray = Ray::new(Point3::new(0.3, 0.2, 5.0), Vec3::new(0.0, 0.0, -1.0));
+
+        let rec = bvh.hit(&ray, 0.001, f64::MAX).unwrap();
+        assert!((rec.t - 4.0).abs() < 1e-12);
+        assert!(rec.front_face);
+        assert!(bvh.bounding_box().is_some());
+
+        // Attributes missing some vertices are ignored rather than read past their end.
+        let partial = cube()
+            .with_normals(vec![Vec3::new(1.0, 0.0, 0.0)])
+            .with_uvs(vec![(0.5, 0.5)])
+            .with_colors(vec![Color::new(1.0, 0.0, 0.0)]);
+        let material = Arc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
+        let rec = partial.to_bvh(material).hit(&ray, 0.001, f64::MAX).unwrap();
+        assert_eq!(rec.normal, Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(rec.color, Color::new(1.0, 1.0, 1.0));
+    }
+}
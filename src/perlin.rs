@@ -0,0 +1,108 @@
+use crate::{
+    definitions::{random_int, random_unit_vector},
+    Point3, Vec3,
+};
+
+const POINT_COUNT: usize = 256;
+
+/// Defines a Perlin gradient noise generator, drawn from the crate's random helpers so that it can
+/// be reproduced with [seed_random](crate::definitions::seed_random).
+#[derive(Clone)]
+pub struct Perlin {
+    gradients: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Perlin {
+    pub fn new() -> Self {
+        Self {
+            gradients: (0..POINT_COUNT).map(|_| random_unit_vector()).collect(),
+            perm_x: Perlin::generate_perm(),
+            perm_y: Perlin::generate_perm(),
+            perm_z: Perlin::generate_perm(),
+        }
+    }
+
+    /// Returns a random permutation of the lattice indices.
+    fn generate_perm() -> Vec<usize> {
+        let mut perm = (0..POINT_COUNT).collect::<Vec<usize>>();
+        for i in (1..POINT_COUNT).rev() {
+            perm.swap(i, random_int(0, i + 1));
+        }
+        perm
+    }
+
+    /// Returns the noise at `point`, roughly between -1.0 and 1.0.
+    pub fn noise(&self, point: &Point3) -> f64 {
+        let floor = point.map(f64::floor);
+        let (u, v, w) = (point.x - floor.x, point.y - floor.y, point.z - floor.z);
+        let (i, j, k) = (floor.x as i64, floor.y as i64, floor.z as i64);
+        let lattice = |n: i64| (n & (POINT_COUNT as i64 - 1)) as usize;
+
+        // Hermite smoothing removes the grid artifacts of plain trilinear interpolation.
+        let (uu, vv, ww) = (
+            u * u * (3.0 - 2.0 * u),
+            v * v * (3.0 - 2.0 * v),
+            w * w * (3.0 - 2.0 * w),
+        );
+
+        let mut accum = 0.0;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let gradient = self.gradients[self.perm_x[lattice(i + di)]
+                        ^ self.perm_y[lattice(j + dj)]
+                        ^ self.perm_z[lattice(k + dk)]];
+                    let (fi, fj, fk) = (di as f64, dj as f64, dk as f64);
+                    let weight = Vec3::new(u - fi, v - fj, w - fk);
+                    accum += (fi * uu + (1.0 - fi) * (1.0 - uu))
+                        * (fj * vv + (1.0 - fj) * (1.0 - vv))
+                        * (fk * ww + (1.0 - fk) * (1.0 - ww))
+                        * gradient.dot(&weight);
+                }
+            }
+        }
+        accum
+    }
+
+    /// Returns the absolute sum of `depth` octaves of noise, each at twice the frequency and half
+    /// the weight of the previous one.
+    pub fn turbulence(&self, point: &Point3, depth: u32) -> f64 {
+        let mut accum = 0.0;
+        let mut temp = *point;
+        let mut weight = 1.0;
+        for _ in 0..depth {
+            accum += weight * self.noise(&temp);
+            weight *= 0.5;
+            temp *= 2.0;
+        }
+        accum.abs()
+    }
+}
+
+impl Default for Perlin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definitions::seed_random;
+
+    #[test]
+    fn seeded_noise_test() {
+        let point = Point3::new(1.3, -2.7, 0.4);
+        seed_random(7);
+        let first = Perlin::new();
+        seed_random(7);
+        let second = Perlin::new();
+
+        assert_eq!(first.noise(&point), second.noise(&point));
+        assert!(first.noise(&point).abs() <= 1.0);
+        assert_eq!(first.noise(&Point3::new(2.0, 3.0, 4.0)), 0.0);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::definitions::PI;
+
+/// Defines the reconstruction filter used to weigh each sample's contribution to the pixels around
+/// it. Radii are given in pixels, a box of radius 0.5 giving the plain average of every sample
+/// inside a pixel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PixelFilter {
+    Box {
+        radius: f64,
+    },
+    Tent {
+        radius: f64,
+    },
+    /// A gaussian of standard deviation `sigma`, shifted down to reach zero at `radius`.
+    Gaussian {
+        radius: f64,
+        sigma: f64,
+    },
+    /// The Mitchell–Netravali cubic, `b` = `c` = 1/3 being the recommended compromise between
+    /// blurring and ringing.
+    Mitchell {
+        radius: f64,
+        b: f64,
+        c: f64,
+    },
+    /// A sinc windowed by a wider sinc, `tau` being the number of lobes kept.
+    Lanczos {
+        radius: f64,
+        tau: f64,
+    },
+}
+
+impl PixelFilter {
+    /// Returns the distance from a sample beyond which pixels are unaffected by it.
+    pub fn radius(&self) -> f64 {
+        match *self {
+            PixelFilter::Box { radius }
+            | PixelFilter::Tent { radius }
+            | PixelFilter::Gaussian { radius, .. }
+            | PixelFilter::Mitchell { radius, .. }
+            | PixelFilter::Lanczos { radius, .. } => radius,
+        }
+    }
+
+    /// Returns the weight of a sample at offset (`dx`, `dy`) from the centre of a pixel.
+    pub fn evaluate(&self, dx: f64, dy: f64) -> f64 {
+        self.evaluate_1d(dx) * self.evaluate_1d(dy)
+    }
+
+    fn evaluate_1d(&self, x: f64) -> f64 {
+        let x = x.abs();
+        if x > self.radius() {
+            return 0.0;
+        }
+        match *self {
+            PixelFilter::Box { .. } => 1.0,
+            PixelFilter::Tent { radius } => radius - x,
+            PixelFilter::Gaussian { radius, sigma } => {
+                let gaussian = |x: f64| (-x * x / (2.0 * sigma * sigma)).exp();
+                (gaussian(x) - gaussian(radius)).max(0.0)
+            }
+            PixelFilter::Mitchell { radius, b, c } => {
+                let x = 2.0 * x / radius;
+                if x > 1.0 {
+                    ((-b - 6.0 * c) * x.powi(3)
+                        + (6.0 * b + 30.0 * c) * x.powi(2)
+                        + (-12.0 * b - 48.0 * c) * x
+                        + (8.0 * b + 24.0 * c))
+                        / 6.0
+                } else {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x.powi(3)
+                        + (-18.0 + 12.0 * b + 6.0 * c) * x.powi(2)
+                        + (6.0 - 2.0 * b))
+                        / 6.0
+                }
+            }
+            PixelFilter::Lanczos { tau, .. } => sinc(x) * sinc(x / tau),
+        }
+    }
+}
+
+impl Default for PixelFilter {
+    fn default() -> Self {
+        PixelFilter::Box { radius: 0.5 }
+    }
+}
+
+/// Returns the normalised sinc function, sin(πx) / πx.
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-5 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evaluate_test() {
+        let (radius, sigma) = (1.5, 0.5);
+        let gaussian = PixelFilter::Gaussian { radius, sigma };
+        let mitchell = PixelFilter::Mitchell {
+            radius: 2.0,
+            b: 1.0 / 3.0,
+            c: 1.0 / 3.0,
+        };
+        let lanczos = PixelFilter::Lanczos {
+            radius: 2.0,
+            tau: 2.0,
+        };
+
+        // The peaks of the 1D filters multiply at the centre of the pixel.
+        let peak = 1.0 - (-radius * radius / (2.0 * sigma * sigma)).exp();
+        assert!((gaussian.evaluate(0.0, 0.0) - peak * peak).abs() < 1e-12);
+        assert!((mitchell.evaluate(0.0, 0.0) - 64.0 / 81.0).abs() < 1e-12);
+        assert_eq!(lanczos.evaluate(0.0, 0.0), 1.0);
+
+        // Each filter falls off smoothly to zero at its radius and stays there.
+        for filter in &[gaussian, mitchell, lanczos] {
+            let radius = filter.radius();
+            assert!(filter.evaluate(radius - 1e-6, 0.0).abs() < 1e-5);
+            assert_eq!(filter.evaluate(radius + 0.01, 0.0), 0.0);
+            assert_eq!(filter.evaluate(0.0, -radius - 0.01), 0.0);
+            assert_eq!(filter.evaluate(radius + 1.0, radius + 1.0), 0.0);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    definitions::PI,
+    frame::{azimuth, Frame},
+    HitRecord, Hittable, Material, Point3, Ray, Vec3,
+};
+
+/// Returns the distance along a local ray to the plane Z = `z`, if it crosses it.
+fn plane_distance(origin: &Point3, direction: &Vec3, z: f64) -> Option<f64> {
+    (direction.z.abs() > 1e-12).then(|| (z - origin.z) / direction.z)
+}
+
+/// Defines an infinite flat surface.
+pub struct Plane {
+    frame: Frame,
+    material: Arc<dyn Material>,
+}
+
+impl Plane {
+    /// Creates the plane going through `point` and facing `normal`. Texture coordinates are the
+    /// distances from `point` along two directions of the plane, so textures repeat every unit.
+    pub fn new(point: Point3, normal: Vec3, material: Arc<dyn Material>) -> Self {
+        Self {
+            frame: Frame::new(point, normal),
+            material,
+        }
+    }
+}
+
+impl Hittable for Plane {
+    /// Provides a definition of hit() for planes.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (origin, direction) = self.frame.ray_to_local(ray);
+        let t = plane_distance(&origin, &direction, 0.0).filter(|t| (t_min..=t_max).contains(t))?;
+        let p = origin + t * direction;
+
+        let rec = HitRecord::new(ray, t, &self.frame.normal, self.material.clone());
+        Some(rec.with_uv((p.x, p.y), (self.frame.tangent, self.frame.bitangent)))
+    }
+}
+
+/// Defines a flat round surface.
+pub struct Disk {
+    frame: Frame,
+    radius: f64,
+    material: Arc<dyn Material>,
+}
+
+impl Disk {
+    /// Creates the disk of the given `radius` centred on `center` and facing `normal`. Texture
+    /// coordinates are the angle around the centre, `u`, and the distance to it, `v`.
+    pub fn new(center: Point3, normal: Vec3, radius: f64, material: Arc<dyn Material>) -> Self {
+        Self {
+            frame: Frame::new(center, normal),
+            radius,
+            material,
+        }
+    }
+}
+
+impl Hittable for Disk {
+    /// Provides a definition of hit() for disks.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (origin, direction) = self.frame.ray_to_local(ray);
+        let t = plane_distance(&origin, &direction, 0.0).filter(|t| (t_min..=t_max).contains(t))?;
+        let p = origin + t * direction;
+        let r = p.x.hypot(p.y);
+        if r > self.radius {
+            return None;
+        }
+
+        let rec = HitRecord::new(ray, t, &self.frame.normal, self.material.clone());
+        Some(rec.with_uv(
+            (azimuth(&p), r / self.radius),
+            disk_partial_derivatives(&self.frame, &p, self.radius),
+        ))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::around_circle(
+            self.frame.origin,
+            &self.frame.normal,
+            self.radius,
+        ))
+    }
+}
+
+/// Returns the derivatives of a point `p` of a disk of the given `radius`, in local coordinates,
+/// with respect to its angle and distance to the centre, scaled to [0, 1].
+fn disk_partial_derivatives(frame: &Frame, p: &Point3, radius: f64) -> (Vec3, Vec3) {
+    let phi = 2.0 * PI * azimuth(p);
+    let dpdu = 2.0 * PI * Vec3::new(-p.y, p.x, 0.0);
+    let dpdv = radius * Vec3::new(phi.cos(), phi.sin(), 0.0);
+    (frame.from_local(&dpdu), frame.from_local(&dpdv))
+}
@@ -0,0 +1,460 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+};
+
+use crate::{mesh::Mesh, Color, Vec3};
+
+/// Defines what can go wrong reading a PLY file.
+#[derive(Debug)]
+pub enum PlyError {
+    Io(io::Error),
+    /// The header is missing, incomplete or uses something this reader does not know of.
+    Header(String),
+    /// The data does not match the header, such as a value that does not parse or a file that
+    /// ends early.
+    Data(String),
+}
+
+impl fmt::Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlyError::Io(error) => write!(f, "{}", error),
+            PlyError::Header(message) => write!(f, "invalid PLY header: {}", message),
+            PlyError::Data(message) => write!(f, "invalid PLY data: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for PlyError {}
+
+impl From<io::Error> for PlyError {
+    fn from(error: io::Error) -> Self {
+        if error.kind() == io::ErrorKind::UnexpectedEof {
+            PlyError::Data("the file ends before all the elements".to_string())
+        } else {
+            PlyError::Io(error)
+        }
+    }
+}
+
+/// Defines how the data after the header is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+/// Defines the types a property value may be stored as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScalarType {
+    Char,
+    UChar,
+    Short,
+    UShort,
+    Int,
+    UInt,
+    Float,
+    Double,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Result<Self, PlyError> {
+        Ok(match name {
+            "char" | "int8" => ScalarType::Char,
+            "uchar" | "uint8" => ScalarType::UChar,
+            "short" | "int16" => ScalarType::Short,
+            "ushort" | "uint16" => ScalarType::UShort,
+            "int" | "int32" => ScalarType::Int,
+            "uint" | "uint32" => ScalarType::UInt,
+            "float" | "float32" => ScalarType::Float,
+            "double" | "float64" => ScalarType::Double,
+            _ => return Err(PlyError::Header(format!("unknown type {}", name))),
+        })
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            ScalarType::Char | ScalarType::UChar => 1,
+            ScalarType::Short | ScalarType::UShort => 2,
+            ScalarType::Int | ScalarType::UInt | ScalarType::Float => 4,
+            ScalarType::Double => 8,
+        }
+    }
+
+    /// Returns the value stored in `bytes`, which are in big endian order.
+    fn decode(&self, bytes: [u8; 8]) -> f64 {
+        match self {
+            ScalarType::Char => bytes[0] as i8 as f64,
+            ScalarType::UChar => bytes[0] as f64,
+            ScalarType::Short => i16::from_be_bytes([bytes[0], bytes[1]]) as f64,
+            ScalarType::UShort => u16::from_be_bytes([bytes[0], bytes[1]]) as f64,
+            ScalarType::Int => i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            ScalarType::UInt => u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            ScalarType::Float => {
+                f32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64
+            }
+            ScalarType::Double => f64::from_be_bytes(bytes),
+        }
+    }
+}
+
+/// Defines the values of one property for every item of an element.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyValues {
+    Scalar(Vec<f64>),
+    /// Lists of values, such as the vertex indices of faces.
+    List(Vec<Vec<f64>>),
+}
+
+/// Defines a property of an element: its name, the type of its values and the values themselves.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Property {
+    pub name: String,
+    pub scalar_type: ScalarType,
+    /// The type of the length of lists, for list properties.
+    pub count_type: Option<ScalarType>,
+    pub values: PropertyValues,
+}
+
+/// Defines a kind of item in a PLY file, such as vertices or faces, with all its items.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Element {
+    pub name: String,
+    pub count: usize,
+    pub properties: Vec<Property>,
+}
+
+impl Element {
+    pub fn property(&self, name: &str) -> Option<&Property> {
+        self.properties
+            .iter()
+            .find(|property| property.name == name)
+    }
+
+    /// Returns the values of the scalar property `name`, if there is one.
+    pub fn scalar(&self, name: &str) -> Option<&[f64]> {
+        match &self.property(name)?.values {
+            PropertyValues::Scalar(values) => Some(values),
+            PropertyValues::List(_) => None,
+        }
+    }
+
+    /// Returns the values of the list property `name`, if there is one.
+    pub fn list(&self, name: &str) -> Option<&[Vec<f64>]> {
+        match &self.property(name)?.values {
+            PropertyValues::List(values) => Some(values),
+            PropertyValues::Scalar(_) => None,
+        }
+    }
+
+    /// Returns the vectors made of the three scalar properties `names`, if there are all three.
+    pub fn vectors(&self, names: [&str; 3]) -> Option<Vec<Vec3>> {
+        let [x, y, z] = names.map(|name| self.scalar(name));
+        let (x, y, z) = (x?, y?, z?);
+        Some(
+            (0..self.count)
+                .map(|i| Vec3::new(x[i], y[i], z[i]))
+                .collect(),
+        )
+    }
+
+    /// Returns the colours made of the `red`, `green` and `blue` properties, if there are all
+    /// three. Colours stored as integers go from 0 to the largest value of their type.
+    pub fn colors(&self) -> Option<Vec<Color>> {
+        let scale = match self.property("red")?.scalar_type {
+            ScalarType::Float | ScalarType::Double => 1.0,
+            ScalarType::UShort | ScalarType::Short => 1.0 / 65535.0,
+            ScalarType::UInt | ScalarType::Int => 1.0 / u32::MAX as f64,
+            ScalarType::UChar | ScalarType::Char => 1.0 / 255.0,
+        };
+        let colors = self.vectors(["red", "green", "blue"])?;
+        Some(colors.into_iter().map(|color| color * scale).collect())
+    }
+}
+
+/// Defines the contents of a PLY file, the polygon file format of the Stanford scanning
+/// repository, in ASCII or binary form.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ply {
+    pub format: PlyFormat,
+    pub elements: Vec<Element>,
+}
+
+impl Ply {
+    pub fn read(mut reader: impl BufRead) -> Result<Self, PlyError> {
+        let mut line = String::new();
+        let mut next_line = |reader: &mut dyn BufRead| -> Result<String, PlyError> {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(PlyError::Header("no end_header line".to_string()));
+            }
+            Ok(line.trim().to_string())
+        };
+        if next_line(&mut reader)? != "ply" {
+            return Err(PlyError::Header("not a PLY file".to_string()));
+        }
+
+        let mut format = None;
+        let mut elements: Vec<Element> = vec![];
+        loop {
+            let line = next_line(&mut reader)?;
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            match words.as_slice() {
+                ["end_header"] => break,
+                ["comment", ..] | ["obj_info", ..] | [] => (),
+                ["format", name, _version] => {
+                    format = Some(match *name {
+                        "ascii" => PlyFormat::Ascii,
+                        "binary_little_endian" => PlyFormat::BinaryLittleEndian,
+                        "binary_big_endian" => PlyFormat::BinaryBigEndian,
+                        _ => return Err(PlyError::Header(format!("unknown format {}", name))),
+                    })
+                }
+                ["element", name, count] => elements.push(Element {
+                    name: name.to_string(),
+                    count: count
+                        .parse()
+                        .map_err(|_| PlyError::Header(format!("invalid count {}", count)))?,
+                    properties: vec![],
+                }),
+                ["property", ..] => {
+                    let element = elements.last_mut().ok_or_else(|| {
+                        PlyError::Header("property outside of an element".to_string())
+                    })?;
+                    let (count_type, scalar_type, name) = match words.as_slice() {
+                        ["property", "list", count_type, scalar_type, name] => (
+                            Some(ScalarType::parse(count_type)?),
+                            ScalarType::parse(scalar_type)?,
+                            name,
+                        ),
+                        ["property", scalar_type, name] => {
+                            (None, ScalarType::parse(scalar_type)?, name)
+                        }
+                        _ => return Err(PlyError::Header(format!("invalid line {}", line))),
+                    };
+                    element.properties.push(Property {
+                        name: name.to_string(),
+                        scalar_type,
+                        count_type,
+                        // The count comes from the file, the values grow as they are read instead.
+                        values: match count_type {
+                            Some(_) => PropertyValues::List(vec![]),
+                            None => PropertyValues::Scalar(vec![]),
+                        },
+                    });
+                }
+                _ => return Err(PlyError::Header(format!("invalid line {}", line))),
+            }
+        }
+        let format = format.ok_or_else(|| PlyError::Header("no format line".to_string()))?;
+
+        match format {
+            PlyFormat::Ascii => {
+                let mut text = String::new();
+                reader.read_to_string(&mut text)?;
+                let mut tokens = text.split_whitespace();
+                read_elements(&mut elements, |_| {
+                    let token = tokens.next().ok_or_else(|| {
+                        PlyError::Data("the file ends before all the elements".to_string())
+                    })?;
+                    token
+                        .parse()
+                        .map_err(|_| PlyError::Data(format!("invalid value {}", token)))
+                })?;
+            }
+            PlyFormat::BinaryLittleEndian | PlyFormat::BinaryBigEndian => {
+                read_elements(&mut elements, |scalar_type| {
+                    let size = scalar_type.size();
+                    let mut bytes = [0; 8];
+                    reader.read_exact(&mut bytes[..size])?;
+                    if format == PlyFormat::BinaryLittleEndian {
+                        bytes[..size].reverse();
+                    }
+                    Ok(scalar_type.decode(bytes))
+                })?;
+            }
+        }
+        Ok(Self { format, elements })
+    }
+
+    /// Reads the PLY file at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, PlyError> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn element(&self, name: &str) -> Option<&Element> {
+        self.elements.iter().find(|element| element.name == name)
+    }
+
+    /// Returns the mesh made of the `vertex` and `face` elements. Positions come from the `x`, `y`
+    /// and `z` properties of vertices, and when present normals from `nx`, `ny` and `nz`, texture
+    /// coordinates from `u` and `v` or `s` and `t`, and colours from `red`, `green` and `blue`.
+    /// Faces list their vertices in `vertex_indices` or `vertex_index`.
+    pub fn to_mesh(&self) -> Result<Mesh, PlyError> {
+        let vertices = self
+            .element("vertex")
+            .ok_or_else(|| PlyError::Data("no vertex element".to_string()))?;
+        let positions = vertices
+            .vectors(["x", "y", "z"])
+            .ok_or_else(|| PlyError::Data("vertices without positions".to_string()))?;
+        let uvs = [["u", "v"], ["s", "t"], ["texture_u", "texture_v"]]
+            .iter()
+            .find_map(|[u, v]| Some((vertices.scalar(u)?, vertices.scalar(v)?)))
+            .map_or(vec![], |(u, v)| {
+                u.iter().copied().zip(v.iter().copied()).collect()
+            });
+
+        let faces = match self.element("face") {
+            Some(faces) => faces
+                .list("vertex_indices")
+                .or_else(|| faces.list("vertex_index"))
+                .ok_or_else(|| PlyError::Data("faces without vertex indices".to_string()))?,
+            None => &[],
+        };
+        let faces = faces
+            .iter()
+            .enumerate()
+            .map(|(f, face)| {
+                if face.len() < 3 {
+                    return Err(PlyError::Data(format!(
+                        "face {} has {} vertices",
+                        f,
+                        face.len()
+                    )));
+                }
+                face.iter()
+                    .map(|&index| {
+                        if index < 0.0 || index >= positions.len() as f64 || index.fract() != 0.0 {
+                            Err(PlyError::Data(format!(
+                                "face {} refers to vertex {} out of {}",
+                                f,
+                                index,
+                                positions.len()
+                            )))
+                        } else {
+                            Ok(index as usize)
+                        }
+                    })
+                    .collect()
+            })
+            .collect::<Result<Vec<Vec<usize>>, PlyError>>()?;
+
+        Ok(Mesh::new(positions, faces)
+            .with_normals(vertices.vectors(["nx", "ny", "nz"]).unwrap_or_default())
+            .with_uvs(uvs)
+            .with_colors(vertices.colors().unwrap_or_default()))
+    }
+}
+
+/// Fills the values of every element, item by item, with the values returned by `next` for each
+/// type in turn.
+fn read_elements(
+    elements: &mut [Element],
+    mut next: impl FnMut(ScalarType) -> Result<f64, PlyError>,
+) -> Result<(), PlyError> {
+    for element in elements
+        .iter_mut()
+        .filter(|element| !element.properties.is_empty())
+    {
+        for _ in 0..element.count {
+            for property in &mut element.properties {
+                let Property {
+                    scalar_type,
+                    count_type,
+                    values,
+                    ..
+                } = property;
+                match values {
+                    PropertyValues::Scalar(values) => values.push(next(*scalar_type)?),
+                    PropertyValues::List(lists) => {
+                        let count = next(count_type.expect("lists always have a count type"))?;
+                        if count < 0.0 {
+                            return Err(PlyError::Data(format!("invalid list length {}", count)));
+                        }
+                        let list = (0..count as usize)
+                            .map(|_| next(*scalar_type))
+                            .collect::<Result<Vec<f64>, PlyError>>()?;
+                        lists.push(list);
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_test() {
+        let header = |format: &str| {
+            format!(
+                "ply\nformat {} 1.0\ncomment made by hand\nelement vertex 2\n\
+                 property float x\nproperty uchar red\nelement face 1\n\
+                 property list uchar int vertex_indices\nend_header\n",
+                format
+            )
+        };
+        let ascii = header("ascii") + "0.5 255\n-2 7\n3 0 1 1\n";
+        let ply = Ply::read(ascii.as_bytes()).unwrap();
+        let vertices = ply.element("vertex").unwrap();
+        assert_eq!(vertices.scalar("x").unwrap(), &[0.5, -2.0]);
+        assert_eq!(vertices.scalar("red").unwrap(), &[255.0, 7.0]);
+        let faces = ply.element("face").unwrap();
+        assert_eq!(
+            faces.list("vertex_indices").unwrap(),
+            &[vec![0.0, 1.0, 1.0]]
+        );
+
+        let mut binary = header("binary_big_endian").into_bytes();
+        binary.extend(0.5f32.to_be_bytes());
+        binary.push(255);
+        binary.extend((-2f32).to_be_bytes());
+        binary.push(7);
+        binary.push(3);
+        for index in [0i32, 1, 1] {
+            binary.extend(index.to_be_bytes());
+        }
+        assert_eq!(Ply::read(binary.as_slice()).unwrap().elements, ply.elements);
+
+        let mut truncated = header("binary_little_endian").into_bytes();
+        truncated.extend(0.5f32.to_le_bytes());
+        assert!(matches!(
+            Ply::read(truncated.as_slice()),
+            Err(PlyError::Data(_))
+        ));
+
+        // Counts are not trusted with allocations, short files fail as they run out.
+        for count in [u64::MAX, 100_000_000_000] {
+            let huge =
+                header("ascii").replace("vertex 2", &format!("vertex {}", count)) + "0.5 255";
+            assert!(matches!(Ply::read(huge.as_bytes()), Err(PlyError::Data(_))));
+        }
+    }
+
+    #[test]
+    fn to_mesh_test() {
+        let text = "ply\nformat ascii 1.0\nelement vertex 4\n\
+                    property float x\nproperty float y\nproperty float z\n\
+                    property uchar red\nproperty uchar green\nproperty uchar blue\n\
+                    element face 1\nproperty list uchar int vertex_indices\nend_header\n\
+                    0 0 0 255 0 0\n1 0 0 255 0 0\n1 1 0 0 0 255\n0 1 0 0 0 255\n4 0 1 2 3\n";
+        let mesh = Ply::read(text.as_bytes()).unwrap().to_mesh().unwrap();
+        assert_eq!(mesh.positions[2], Vec3::new(1.0, 1.0, 0.0));
+        assert_eq!(mesh.colors[3], Color::new(0.0, 0.0, 1.0));
+        assert_eq!(mesh.faces, vec![vec![0, 1, 2, 3]]);
+        assert!(mesh.normals.is_empty() && mesh.uvs.is_empty());
+
+        let out_of_range = text.replace("4 0 1 2 3", "4 0 1 2 4");
+        assert!(matches!(
+            Ply::read(out_of_range.as_bytes()).unwrap().to_mesh(),
+            Err(PlyError::Data(_))
+        ));
+    }
+}
@@ -0,0 +1,174 @@
+use std::{path::Path, sync::Arc};
+
+use crate::{
+    aabb::Aabb,
+    bvh::Bvh,
+    definitions::near_zero,
+    material::VertexTinted,
+    plane::Disk,
+    ply::{Ply, PlyError},
+    sphere::Sphere,
+    Color, HitRecord, Hittable, Material, Point3, Ray, Vec3,
+};
+
+/// Defines how every point of a [PointCloud] is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Splat {
+    /// A disk facing the normal of the point, or a sphere for points without one.
+    Disk,
+    Sphere,
+}
+
+/// Defines a point of a [PointCloud], with its normal when it has one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CloudPoint {
+    pub position: Point3,
+    pub normal: Option<Vec3>,
+    pub color: Color,
+}
+
+/// Defines the splat of a single point, giving its hits the colour of the point.
+struct PointSplat<T: Hittable> {
+    shape: T,
+    color: Color,
+}
+
+impl<T: Hittable> Hittable for PointSplat<T> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let rec = self.shape.hit(ray, t_min, t_max)?;
+        Some(HitRecord {
+            color: self.color,
+            ..rec
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.shape.bounding_box()
+    }
+}
+
+/// Defines a point cloud, such as a scan, drawn as a small disk or sphere per point held in a
+/// bounding volume hierarchy.
+pub struct PointCloud {
+    bvh: Bvh,
+}
+
+impl PointCloud {
+    /// Creates the cloud with every point drawn as a `splat` of the given `radius`. All the points
+    /// share `material`, tinted by the colour of each, see [VertexTinted].
+    pub fn new(
+        points: &[CloudPoint],
+        splat: Splat,
+        radius: f64,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        let material: Arc<dyn Material> = Arc::new(VertexTinted::new(material));
+        let splats = points
+            .iter()
+            .map(|point| -> Arc<dyn Hittable> {
+                let (color, material) = (point.color, material.clone());
+                match (splat, point.normal) {
+                    (Splat::Disk, Some(normal)) => Arc::new(PointSplat {
+                        shape: Disk::new(point.position, normal, radius, material),
+                        color,
+                    }),
+                    _ => Arc::new(PointSplat {
+                        shape: Sphere::new(point.position, radius, material),
+                        color,
+                    }),
+                }
+            })
+            .collect();
+        Self {
+            bvh: Bvh::new(splats),
+        }
+    }
+
+    /// Reads the points of a PLY file: the `x`, `y` and `z` properties of its vertices, with
+    /// their normals from `nx`, `ny` and `nz` and colours from `red`, `green` and `blue` when
+    /// present. Points without a colour are white.
+    pub fn points_from_ply(ply: &Ply) -> Result<Vec<CloudPoint>, PlyError> {
+        let vertices = ply
+            .element("vertex")
+            .ok_or_else(|| PlyError::Data("no vertex element".to_string()))?;
+        let positions = vertices
+            .vectors(["x", "y", "z"])
+            .ok_or_else(|| PlyError::Data("vertices without positions".to_string()))?;
+        let normals = vertices.vectors(["nx", "ny", "nz"]);
+        let colors = vertices.colors();
+
+        Ok((0..vertices.count)
+            .map(|i| CloudPoint {
+                position: positions[i],
+                // Some scanners write zero normals for points they could not orient.
+                normal: normals
+                    .as_ref()
+                    .map(|normals| normals[i])
+                    .filter(|normal| !near_zero(*normal)),
+                color: colors
+                    .as_ref()
+                    .map_or(Color::new(1.0, 1.0, 1.0), |colors| colors[i]),
+            })
+            .collect())
+    }
+
+    /// Reads the cloud from the PLY file at `path`, ASCII or binary, as described in
+    /// [points_from_ply](PointCloud::points_from_ply).
+    pub fn open(
+        path: impl AsRef<Path>,
+        splat: Splat,
+        radius: f64,
+        material: Arc<dyn Material>,
+    ) -> Result<Self, PlyError> {
+        let points = Self::points_from_ply(&Ply::open(path)?)?;
+        Ok(Self::new(&points, splat, radius, material))
+    }
+}
+
+impl Hittable for PointCloud {
+    /// Provides a definition of hit() for point clouds, the nearest hit among their splats.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.bvh.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bvh.bounding_box()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{material::Lambertian, INFINITY};
+
+    #[test]
+    fn point_cloud_test() {
+        let text = "ply\nformat ascii 1.0\nelement vertex 2\n\
+                    property float x\nproperty float y\nproperty float z\n\
+                    property float nx\nproperty float ny\nproperty float nz\n\
+                    property uchar red\nproperty uchar green\nproperty uchar blue\nend_header\n\
+                    0 0 0 0 0 1 255 0 0\n\
+                    2 0 0 0 0 0 0 0 255\n";
+        let points = PointCloud::points_from_ply(&Ply::read(text.as_bytes()).unwrap()).unwrap();
+        assert_eq!(points[0].normal, Some(Vec3::new(0.0, 0.0, 1.0)));
+        assert_eq!(points[1].normal, None);
+        let grey = Color::new(0.5, 0.5, 0.5);
+        let cloud = PointCloud::new(&points, Splat::Disk, 0.25, Arc::new(Lambertian::new(grey)));
+        let down = |x: f64| Ray::new(Point3::new(x, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+
+        // The oriented point is a flat disk, the other one a sphere, both tinting one material.
+        let disk = cloud.hit(&down(0.1), 0.001, INFINITY).unwrap();
+        assert!((disk.t - 5.0).abs() < 1e-9);
+        assert_eq!(disk.color, Color::new(1.0, 0.0, 0.0));
+        assert!((disk.material.albedo(&disk) - Color::new(0.5, 0.0, 0.0)).norm() < 1e-9);
+        let sphere = cloud.hit(&down(2.0), 0.001, INFINITY).unwrap();
+        assert!((sphere.t - 4.75).abs() < 1e-9);
+        assert!((sphere.material.albedo(&sphere) - Color::new(0.0, 0.0, 0.5)).norm() < 1e-9);
+        assert!(Arc::ptr_eq(&disk.material, &sphere.material));
+        assert!(cloud.hit(&down(0.3), 0.001, INFINITY).is_none());
+        assert!(cloud.hit(&down(1.0), 0.001, INFINITY).is_none());
+
+        let bbox = cloud.bounding_box().unwrap();
+        assert!(bbox.min.x <= -0.25 && bbox.max.x >= 2.25);
+    }
+}
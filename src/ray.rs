@@ -1,4 +1,4 @@
-use crate::{Color, Hittable, HittableList, Vec3, INFINITY};
+use crate::{Color, HitRecord, Hittable, HittableList, Vec3, INFINITY};
 
 /// Defines an alias for Vec3, used to define a point in 3-dimensional co-ordinate space.
 pub type Point3 = Vec3;
@@ -8,12 +8,22 @@ pub type Point3 = Vec3;
 pub struct Ray {
     orig: Point3,
     dir: Vec3,
+    wavelength: Option<f64>,
 }
 
 impl Ray {
     /// This function creates a new Ray.
     pub fn new(orig: Point3, dir: Vec3) -> Self {
-        Self { orig, dir }
+        Self {
+            orig,
+            dir,
+            wavelength: None,
+        }
+    }
+
+    /// Returns the same Ray, carrying a single wavelength (in nanometres) for spectral rendering.
+    pub fn with_wavelength(self, wavelength: Option<f64>) -> Self {
+        Self { wavelength, ..self }
     }
 
     /// Returns the origin of the given Ray.
@@ -26,6 +36,11 @@ impl Ray {
         self.dir
     }
 
+    /// Returns the wavelength carried by the given Ray, if it is traced spectrally.
+    pub fn wavelength(&self) -> Option<f64> {
+        self.wavelength
+    }
+
     /// Returns point along Ray at `t`.
     pub fn at(&self, t: f64) -> Point3 {
         self.orig + self.dir * t
@@ -35,22 +50,46 @@ impl Ray {
     pub fn color(&self, world: &HittableList, depth: i32) -> Color {
         if depth <= 0 {
             Color::new(0.0, 0.0, 0.0)
-        } else if let Some(rec) = world.hit(self, 0.001, INFINITY) {
-            match rec
-                .material
-                .scatter(self, &rec, &Ray::new(rec.point, rec.normal))
-            {
-                Some((attenuation, scattered)) => {
-                    attenuation.zip_map(&scattered.color(world, depth - 1), |l, r| l * r)
-                }
-                None => Color::new(0.0, 0.0, 0.0),
-            }
         } else {
-            let unit_dir = self.dir.normalize();
-            let t = 0.5 * (unit_dir.y + 1.0);
-            (1.0 - t) * Color::new(1.0, 1.0, 1.0) + t * Color::new(0.5, 0.7, 1.0)
+            self.trace(world, depth).0
         }
     }
+
+    /// Returns the expected color along the Ray together with the record of the first object it
+    /// hits, which the auxiliary buffers are filled from.
+    pub fn trace(&self, world: &HittableList, depth: i32) -> (Color, Option<HitRecord>) {
+        let rec = world.hit(self, 0.001, INFINITY);
+        let color = match &rec {
+            _ if depth <= 0 => Color::new(0.0, 0.0, 0.0),
+            Some(rec) => {
+                let emitted = rec.material.emitted(rec);
+                match rec
+                    .material
+                    .scatter(self, rec, &Ray::new(rec.point, rec.normal))
+                {
+                    Some((attenuation, scattered)) => {
+                        emitted
+                            + attenuation.zip_map(
+                                &scattered
+                                    .with_wavelength(self.wavelength)
+                                    .color(world, depth - 1),
+                                |l, r| l * r,
+                            )
+                    }
+                    None => emitted,
+                }
+            }
+            None => self.background(),
+        };
+        (color, rec)
+    }
+
+    /// Returns the color of the sky seen along the Ray.
+    pub fn background(&self) -> Color {
+        let unit_dir = self.dir.normalize();
+        let t = 0.5 * (unit_dir.y + 1.0);
+        (1.0 - t) * Color::new(1.0, 1.0, 1.0) + t * Color::new(0.5, 0.7, 1.0)
+    }
 }
 
 #[cfg(test)]
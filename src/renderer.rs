@@ -0,0 +1,275 @@
+use rayon::prelude::*;
+use std::{
+    io::{stderr, Write},
+    ops::Range,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use crate::{
+    aov::AovBuffer,
+    camera::CameraModel,
+    definitions::{sample_2d, set_sampler, start_pixel_sample},
+    framebuffer::FrameBuffer,
+    pixel_filter::PixelFilter,
+    sampler::{IndependentSampler, Sampler},
+    spectrum::sample_wavelength,
+    Color, HitRecord, HittableList, Material,
+};
+
+/// Defines when adaptive sampling stops refining a pixel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveSampling {
+    /// Relative standard error below which a pixel is considered converged.
+    pub threshold: f64,
+    /// Number of samples a pixel never goes past.
+    pub max_samples_per_pixel: i32,
+}
+
+/// Defines the settings used to render a world as seen by a camera.
+#[derive(Clone)]
+pub struct Renderer {
+    width: usize,
+    height: usize,
+    samples_per_pixel: i32,
+    max_depth: i32,
+    spectral: bool,
+    filter: PixelFilter,
+    sampler: Arc<dyn Sampler>,
+    adaptive: Option<AdaptiveSampling>,
+    progress: bool,
+    materials: Vec<Arc<dyn Material>>,
+}
+
+impl Renderer {
+    /// Used to set the image size, the number of samples per pixel and the maximum bounce depth.
+    pub fn new(width: usize, height: usize, samples_per_pixel: i32, max_depth: i32) -> Self {
+        Self {
+            width,
+            height,
+            samples_per_pixel,
+            max_depth,
+            spectral: false,
+            filter: PixelFilter::default(),
+            sampler: Arc::new(IndependentSampler),
+            adaptive: None,
+            progress: false,
+            materials: vec![],
+        }
+    }
+
+    /// Traces a single random wavelength per path, so that dispersive materials split light.
+    pub fn spectral(mut self, spectral: bool) -> Self {
+        self.spectral = spectral;
+        self
+    }
+
+    /// Sets the filter used to reconstruct pixels from samples.
+    pub fn filter(mut self, filter: PixelFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Sets the sampler the pixel, lens, wavelength and scattering dimensions are drawn from.
+    pub fn sampler(mut self, sampler: Arc<dyn Sampler>) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    /// Keeps sampling the pixels whose estimated error is above the threshold once every pixel
+    /// got `samples_per_pixel` samples, in batches of that many, up to the given maximum.
+    pub fn adaptive(mut self, adaptive: AdaptiveSampling) -> Self {
+        self.adaptive = Some(adaptive);
+        self
+    }
+
+    /// Prints the number of scanlines left to trace to stderr while rendering.
+    pub fn progress(mut self, progress: bool) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Numbers the materials of the material ID buffer by their index in `materials`, the same
+    /// from one render to the next. Other materials get no identifier.
+    pub fn material_ids(mut self, materials: Vec<Arc<dyn Material>>) -> Self {
+        self.materials = materials;
+        self
+    }
+
+    /// Returns the identifier of the material of a hit, see [material_ids](Renderer::material_ids).
+    fn material_id(&self, rec: &HitRecord) -> Option<usize> {
+        let material = Arc::as_ptr(&rec.material) as *const ();
+        self.materials
+            .iter()
+            .position(|other| Arc::as_ptr(other) as *const () == material)
+    }
+
+    /// Renders every pixel of the image, scanlines being traced in parallel.
+    pub fn render(&self, camera: &dyn CameraModel, world: &HittableList) -> FrameBuffer {
+        self.render_passes(camera, world, false).0
+    }
+
+    /// Renders the image along with its auxiliary buffers, in the same pass.
+    pub fn render_with_aovs(
+        &self,
+        camera: &dyn CameraModel,
+        world: &HittableList,
+    ) -> (FrameBuffer, AovBuffer) {
+        let (image, aovs) = self.render_passes(camera, world, true);
+        (
+            image,
+            aovs.unwrap_or_else(|| AovBuffer::new(self.width, self.height)),
+        )
+    }
+
+    fn render_passes(
+        &self,
+        camera: &dyn CameraModel,
+        world: &HittableList,
+        aovs: bool,
+    ) -> (FrameBuffer, Option<AovBuffer>) {
+        let samples_per_pixel = self.samples_per_pixel.max(0) as u32;
+        let (mut image, mut aov_buffer) =
+            self.render_pass(camera, world, aovs, &|_, _| 0..samples_per_pixel);
+
+        if let Some(adaptive) = self.adaptive {
+            let max = adaptive.max_samples_per_pixel.max(0) as u32;
+            let batch = samples_per_pixel.max(1);
+            loop {
+                let samples = |x, y| {
+                    let count = image.sample_count(x, y);
+                    if count < max && image.relative_error(x, y) > adaptive.threshold {
+                        count..(count + batch).min(max)
+                    } else {
+                        count..count
+                    }
+                };
+                let active = (0..self.height)
+                    .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+                    .any(|(x, y)| !samples(x, y).is_empty());
+                if !active {
+                    break;
+                }
+                let (pass, aov_pass) = self.render_pass(camera, world, aovs, &samples);
+                image = image.merge(pass);
+                aov_buffer = merge_aovs(aov_buffer, aov_pass);
+            }
+        }
+
+        (image, aov_buffer)
+    }
+
+    /// Traces the samples of each pixel whose indices are given by `samples`, along with the
+    /// auxiliary buffers if `aovs` is set.
+    fn render_pass(
+        &self,
+        camera: &dyn CameraModel,
+        world: &HittableList,
+        aovs: bool,
+        samples: &(dyn Fn(usize, usize) -> Range<u32> + Sync),
+    ) -> (FrameBuffer, Option<AovBuffer>) {
+        let empty = || {
+            (
+                FrameBuffer::new(self.width, self.height),
+                aovs.then(|| AovBuffer::new(self.width, self.height)),
+            )
+        };
+        let remaining = AtomicUsize::new(self.height);
+        (0..self.height)
+            .into_par_iter()
+            .fold(empty, |(mut image, mut aov_buffer), j| {
+                set_sampler(Some(self.sampler.clone()));
+                for i in 0..self.width {
+                    for s in samples(i, j) {
+                        start_pixel_sample(i, j, s);
+                        let (dx, dy) = sample_2d();
+                        let (x, y) = (i as f64 + dx, j as f64 + dy);
+                        let (u, v) = (x / self.width as f64, y / self.height as f64);
+                        let ray = match camera.try_get_ray(u, v) {
+                            Some(ray) => ray,
+                            None => {
+                                image.splat(x, y, Color::new(0.0, 0.0, 0.0), &self.filter);
+                                continue;
+                            }
+                        };
+                        let lambda = self.spectral.then(sample_wavelength);
+                        let (color, rec) = ray.with_wavelength(lambda).trace(world, self.max_depth);
+                        if let Some(aov_buffer) = aov_buffer.as_mut() {
+                            let material_id = rec.as_ref().and_then(|rec| self.material_id(rec));
+                            aov_buffer.add_sample(i, j, &ray, rec.as_ref(), material_id);
+                        }
+                        match lambda {
+                            Some(lambda) => image.splat_spectral(x, y, color, lambda, &self.filter),
+                            None => image.splat(x, y, color, &self.filter),
+                        }
+                    }
+                }
+                set_sampler(None);
+                if self.progress {
+                    let remaining = remaining.fetch_sub(1, Ordering::Relaxed) - 1;
+                    eprint!("\rScanlines remaining: {} ", remaining);
+                    stderr().flush().unwrap();
+                }
+                (image, aov_buffer)
+            })
+            .reduce(empty, |(image, aov_buffer), (other, other_aovs)| {
+                (image.merge(other), merge_aovs(aov_buffer, other_aovs))
+            })
+    }
+}
+
+fn merge_aovs(first: Option<AovBuffer>, second: Option<AovBuffer>) -> Option<AovBuffer> {
+    match (first, second) {
+        (Some(first), Some(second)) => Some(first.merge(second)),
+        (first, second) => first.or(second),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{camera::Camera, sphere::Sphere, Lambertian, Point3};
+
+    #[test]
+    fn render_size_test() {
+        let camera =
+            Camera::builder(Point3::new(0.0, 0.0, 0.0), Point3::new(0.0, 0.0, -1.0)).build();
+        let world = HittableList::default();
+
+        let image = Renderer::new(1, 1, 4, 2).render(&camera, &world);
+        assert!(image.pixel(0, 0).iter().all(|c| c.is_finite()));
+        let image = Renderer::new(0, 0, 4, 2).render(&camera, &world);
+        assert_eq!((image.width(), image.height()), (0, 0));
+    }
+
+    #[test]
+    fn aov_test() {
+        let camera = Camera::builder(Point3::new(0.0, 0.0, 0.0), Point3::new(0.0, 0.0, -1.0))
+            .aspect_ratio(1.0)
+            .build();
+        let grey: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let red: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.8, 0.1, 0.1)));
+        let mut world = HittableList::default();
+        world.add(Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, -3.0),
+            1.0,
+            red.clone(),
+        )));
+
+        let renderer = Renderer::new(3, 3, 8, 4).material_ids(vec![grey, red]);
+        let (_, aovs) = renderer.render_with_aovs(&camera, &world);
+        assert_eq!(aovs.object_id(1, 1), Some(0));
+        assert_eq!(aovs.material_id(1, 1), Some(1));
+        // Grazing rays reach the sphere at most 2√2 away, at the tangent points.
+        let depth = aovs.depth(1, 1).unwrap();
+        assert!((2.0..=8f64.sqrt()).contains(&depth));
+        assert_eq!((aovs.object_id(0, 0), aovs.material_id(0, 0)), (None, None));
+        assert!(aovs.depth(0, 0).is_none());
+
+        // Materials left out of the list have no identifier.
+        let (_, aovs) = Renderer::new(3, 3, 8, 4).render_with_aovs(&camera, &world);
+        assert_eq!(aovs.material_id(1, 1), None);
+    }
+}
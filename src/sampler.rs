@@ -0,0 +1,283 @@
+use crate::definitions::random_double;
+
+/// Defines the interface of a sample generator. Every sample of a pixel is a point in an
+/// unbounded number of dimensions, consumed in order by the renderer: the position in the pixel,
+/// the position on the lens, the wavelength and then the scattering decisions of each bounce.
+pub trait Sampler: Sync + Send {
+    /// Returns the coordinate `dimension` of sample `index` of pixel (`x`, `y`), in [0, 1).
+    fn get_1d(&self, x: usize, y: usize, index: u32, dimension: u32) -> f64;
+
+    /// Returns the coordinates `dimension` and `dimension + 1` of sample `index` of pixel (`x`,
+    /// `y`), stratified together where the sampler allows it.
+    fn get_2d(&self, x: usize, y: usize, index: u32, dimension: u32) -> (f64, f64) {
+        (
+            self.get_1d(x, y, index, dimension),
+            self.get_1d(x, y, index, dimension + 1),
+        )
+    }
+}
+
+/// Mixes a pixel and a dimension into a seed, so that every sequence is decorrelated from the
+/// others.
+fn hash(x: usize, y: usize, dimension: u32, seed: u64) -> u64 {
+    let mut h = seed;
+    for value in [x as u64, y as u64, dimension as u64] {
+        // SplitMix64 finaliser.
+        h = h.wrapping_add(value).wrapping_add(0x9e3779b97f4a7c15);
+        h = (h ^ (h >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        h = (h ^ (h >> 27)).wrapping_mul(0x94d049bb133111eb);
+        h ^= h >> 31;
+    }
+    h
+}
+
+/// Converts 32 random bits to a double in [0, 1).
+fn to_unit(bits: u32) -> f64 {
+    bits as f64 / 4294967296.0
+}
+
+/// Returns element `i` of a pseudo-random permutation of `0..l` chosen by `p`, following Kensler's
+/// "Correlated Multi-Jittered Sampling".
+fn permutation_element(mut i: u32, l: u32, p: u32) -> u32 {
+    let mut w = l - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < l {
+            break;
+        }
+    }
+    (i.wrapping_add(p)) % l
+}
+
+/// Defines the sampler drawing every dimension independently, as done without any sampler.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IndependentSampler;
+
+impl Sampler for IndependentSampler {
+    fn get_1d(&self, _x: usize, _y: usize, _index: u32, _dimension: u32) -> f64 {
+        random_double(0.0, 1.0)
+    }
+}
+
+/// Defines a jittered sampler, splitting every dimension into as many strata as there are samples
+/// per pixel and every pair of dimensions into a grid of about as many cells.
+#[derive(Debug, Clone, Copy)]
+pub struct StratifiedSampler {
+    samples_per_pixel: u32,
+    seed: u64,
+}
+
+impl StratifiedSampler {
+    pub fn new(samples_per_pixel: u32) -> Self {
+        Self {
+            samples_per_pixel: samples_per_pixel.max(1),
+            seed: 0,
+        }
+    }
+
+    /// Used to decorrelate renders done with the same settings.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Returns the stratum sample `index` falls into, out of `strata`.
+    fn stratum(&self, x: usize, y: usize, index: u32, dimension: u32, strata: u32) -> u32 {
+        let p = hash(x, y, dimension, self.seed) as u32;
+        permutation_element(index % strata, strata, p)
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn get_1d(&self, x: usize, y: usize, index: u32, dimension: u32) -> f64 {
+        let n = self.samples_per_pixel;
+        let stratum = self.stratum(x, y, index, dimension, n);
+        (stratum as f64 + random_double(0.0, 1.0)) / n as f64
+    }
+
+    fn get_2d(&self, x: usize, y: usize, index: u32, dimension: u32) -> (f64, f64) {
+        let nx = (self.samples_per_pixel as f64).sqrt().ceil() as u32;
+        let ny = self.samples_per_pixel.div_ceil(nx);
+        let stratum = self.stratum(x, y, index, dimension, nx * ny);
+        (
+            ((stratum % nx) as f64 + random_double(0.0, 1.0)) / nx as f64,
+            ((stratum / nx) as f64 + random_double(0.0, 1.0)) / ny as f64,
+        )
+    }
+}
+
+/// Bases of the Halton sequence, one per dimension.
+const PRIMES: [u32; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131,
+];
+
+/// Returns the digits of `index` in `base`, mirrored around the radix point.
+fn radical_inverse(base: u32, mut index: u32) -> f64 {
+    let inv_base = 1.0 / base as f64;
+    let (mut reversed, mut inv_base_n) = (0.0, 1.0);
+    while index > 0 {
+        inv_base_n *= inv_base;
+        reversed += (index % base) as f64 * inv_base_n;
+        index /= base;
+    }
+    reversed
+}
+
+/// Defines a sampler following the Halton sequence, shifted per pixel by a random rotation.
+/// Dimensions past the supported ones fall back to independent samples.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HaltonSampler {
+    seed: u64,
+}
+
+impl HaltonSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Used to decorrelate renders done with the same settings.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn get_1d(&self, x: usize, y: usize, index: u32, dimension: u32) -> f64 {
+        match PRIMES.get(dimension as usize) {
+            Some(&base) => {
+                let offset = to_unit((hash(x, y, dimension, self.seed) >> 32) as u32);
+                (radical_inverse(base, index) + offset).fract()
+            }
+            None => random_double(0.0, 1.0),
+        }
+    }
+}
+
+/// Returns the bits of one of the first two dimensions of the Sobol sequence, which together form
+/// a (0, 2)-sequence.
+fn sobol(mut index: u32, dimension: u32) -> u32 {
+    let mut direction = 1u32 << 31;
+    let mut result = 0;
+    while index != 0 {
+        if index & 1 != 0 {
+            result ^= direction;
+        }
+        index >>= 1;
+        direction = if dimension == 0 {
+            direction >> 1
+        } else {
+            direction ^ (direction >> 1)
+        };
+    }
+    result
+}
+
+/// Hash based permutation applying to the bits of `x` from the lowest, following Laine and
+/// Karras.
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x
+}
+
+/// Applies an Owen scramble to the bits of `x`, from the highest.
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+/// Defines a sampler following the Sobol sequence, with Owen scrambling and a shuffled sample
+/// order per pair of dimensions, following Burley's "Practical Hash-based Owen Scrambling".
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SobolSampler {
+    seed: u64,
+}
+
+impl SobolSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Used to decorrelate renders done with the same settings.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn get_1d(&self, x: usize, y: usize, index: u32, dimension: u32) -> f64 {
+        self.get_2d(x, y, index, dimension).0
+    }
+
+    fn get_2d(&self, x: usize, y: usize, index: u32, dimension: u32) -> (f64, f64) {
+        let h = hash(x, y, dimension, self.seed);
+        let (shuffle, scramble) = (h as u32, (h >> 32) as u32);
+        let index = nested_uniform_scramble(index, shuffle);
+        (
+            to_unit(nested_uniform_scramble(sobol(index, 0), scramble)),
+            to_unit(nested_uniform_scramble(
+                sobol(index, 1),
+                scramble ^ 0x9e3779b9,
+            )),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks that the first 16 samples land in different cells of a 4 × 4 grid.
+    fn assert_stratified(sampler: &dyn Sampler) {
+        let mut cells = [false; 16];
+        for index in 0..16 {
+            let (u, v) = sampler.get_2d(3, 5, index, 0);
+            let cell = (u * 4.0) as usize + 4 * (v * 4.0) as usize;
+            assert!(!cells[cell]);
+            cells[cell] = true;
+        }
+    }
+
+    #[test]
+    fn stratification_test() {
+        assert_stratified(&StratifiedSampler::new(16));
+        assert_stratified(&SobolSampler::new().with_seed(42));
+    }
+
+    #[test]
+    fn permutation_test() {
+        let mut seen = (0..10)
+            .map(|i| permutation_element(i, 10, 1234))
+            .collect::<Vec<u32>>();
+        seen.sort_unstable();
+
+        assert_eq!(seen, (0..10).collect::<Vec<u32>>());
+    }
+}
@@ -0,0 +1,304 @@
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use crate::{
+    bvh::Bvh,
+    transform::{Transform, Transformed},
+    Hittable, HittableList,
+};
+
+/// Defines what can go wrong editing a [SceneGraph].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SceneError {
+    /// A node of that name is already in the graph.
+    DuplicateName(String),
+    /// No node of that name is in the graph.
+    UnknownNode(String),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::DuplicateName(name) => write!(f, "a node named {} already exists", name),
+            SceneError::UnknownNode(name) => write!(f, "no node named {}", name),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+/// Defines a node of a [SceneGraph]: a transformation relative to its parent and an optional
+/// object placed by it, under a name unique in the graph.
+#[derive(Clone)]
+pub struct SceneNode {
+    name: String,
+    pub transform: Transform,
+    pub object: Option<Arc<dyn Hittable>>,
+}
+
+impl SceneNode {
+    /// Creates an empty node, which can still group and place its children.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            transform: Transform::identity(),
+            object: None,
+        }
+    }
+
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    pub fn with_object(mut self, object: Arc<dyn Hittable>) -> Self {
+        self.object = Some(object);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Defines a node of the graph along with its links to the others.
+#[derive(Clone)]
+struct Entry {
+    node: SceneNode,
+    parent: Option<String>,
+    children: Vec<String>,
+}
+
+/// Defines a scene as a tree of named nodes, each placed relative to its parent, which is
+/// flattened into a world of objects in a bounding volume hierarchy for rendering.
+#[derive(Clone, Default)]
+pub struct SceneGraph {
+    entries: HashMap<String, Entry>,
+    roots: Vec<String>,
+}
+
+impl SceneGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Used to add `node` as the last child of the node named `parent`, or at the top of the
+    /// graph for None.
+    pub fn add(&mut self, node: SceneNode, parent: Option<&str>) -> Result<(), SceneError> {
+        if self.entries.contains_key(&node.name) {
+            return Err(SceneError::DuplicateName(node.name));
+        }
+        let name = node.name.clone();
+        match parent {
+            Some(parent) => self
+                .entries
+                .get_mut(parent)
+                .ok_or_else(|| SceneError::UnknownNode(parent.to_string()))?
+                .children
+                .push(name.clone()),
+            None => self.roots.push(name.clone()),
+        }
+        self.entries.insert(
+            name,
+            Entry {
+                node,
+                parent: parent.map(str::to_string),
+                children: vec![],
+            },
+        );
+        Ok(())
+    }
+
+    pub fn node(&self, name: &str) -> Option<&SceneNode> {
+        self.entries.get(name).map(|entry| &entry.node)
+    }
+
+    pub fn node_mut(&mut self, name: &str) -> Option<&mut SceneNode> {
+        self.entries.get_mut(name).map(|entry| &mut entry.node)
+    }
+
+    /// Returns the name of the parent of the node named `name`, None for nodes at the top.
+    pub fn parent(&self, name: &str) -> Option<&str> {
+        self.entries.get(name)?.parent.as_deref()
+    }
+
+    /// Returns the names of the children of the node named `name`, in the order they were added.
+    pub fn children(&self, name: &str) -> &[String] {
+        self.entries
+            .get(name)
+            .map_or(&[], |entry| entry.children.as_slice())
+    }
+
+    /// Returns the names of the nodes at the top of the graph.
+    pub fn roots(&self) -> &[String] {
+        &self.roots
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the transformation from the space of the node named `name` to the world, through
+    /// all of its ancestors.
+    pub fn world_transform(&self, name: &str) -> Option<Transform> {
+        let mut entry = self.entries.get(name)?;
+        let mut transform = entry.node.transform;
+        while let Some(parent) = &entry.parent {
+            entry = &self.entries[parent];
+            transform = transform.then(&entry.node.transform);
+        }
+        Some(transform)
+    }
+
+    /// Used to remove the node named `name` along with all of its descendants. Returns the node.
+    pub fn remove(&mut self, name: &str) -> Result<SceneNode, SceneError> {
+        let entry = self
+            .entries
+            .remove(name)
+            .ok_or_else(|| SceneError::UnknownNode(name.to_string()))?;
+        let siblings = match &entry.parent {
+            Some(parent) => &mut self.entries.get_mut(parent).unwrap().children,
+            None => &mut self.roots,
+        };
+        siblings.retain(|sibling| sibling != name);
+
+        let mut descendants = entry.children.clone();
+        while let Some(descendant) = descendants.pop() {
+            descendants.extend(self.entries.remove(&descendant).unwrap().children);
+        }
+        Ok(entry.node)
+    }
+
+    /// Used to put `node` in the place of the node named `name`, with the same parent and
+    /// children. Returns the replaced node.
+    pub fn replace(&mut self, name: &str, node: SceneNode) -> Result<SceneNode, SceneError> {
+        if !self.entries.contains_key(name) {
+            return Err(SceneError::UnknownNode(name.to_string()));
+        }
+        if node.name == name {
+            let entry = self.entries.get_mut(name).unwrap();
+            return Ok(std::mem::replace(&mut entry.node, node));
+        }
+        if self.entries.contains_key(&node.name) {
+            return Err(SceneError::DuplicateName(node.name));
+        }
+
+        // A renamed node, whose parent and children are pointed to the new name.
+        let mut entry = self.entries.remove(name).unwrap();
+        let new_name = node.name.clone();
+        let siblings = match &entry.parent {
+            Some(parent) => &mut self.entries.get_mut(parent).unwrap().children,
+            None => &mut self.roots,
+        };
+        for sibling in siblings.iter_mut().filter(|sibling| *sibling == name) {
+            *sibling = new_name.clone();
+        }
+        for child in &entry.children {
+            self.entries.get_mut(child).unwrap().parent = Some(new_name.clone());
+        }
+        let replaced = std::mem::replace(&mut entry.node, node);
+        self.entries.insert(new_name, entry);
+        Ok(replaced)
+    }
+
+    /// Returns the objects of every node, placed in the world, parents before their children.
+    pub fn placed_objects(&self) -> Vec<Arc<dyn Hittable>> {
+        let mut objects = vec![];
+        let mut stack = self
+            .roots
+            .iter()
+            .rev()
+            .map(|name| (name, Transform::identity()))
+            .collect::<Vec<_>>();
+        while let Some((name, parent)) = stack.pop() {
+            let entry = &self.entries[name];
+            let transform = entry.node.transform.then(&parent);
+            if let Some(object) = &entry.node.object {
+                objects.push(match transform.is_identity() {
+                    true => object.clone(),
+                    false => Arc::new(Transformed::new(object.clone(), transform)),
+                });
+            }
+            stack.extend(entry.children.iter().rev().map(|child| (child, transform)));
+        }
+        objects
+    }
+
+    /// Returns the placed objects of the graph in a bounding volume hierarchy, ready to render.
+    pub fn flatten(&self) -> HittableList {
+        let mut world = HittableList::default();
+        world.add(Arc::new(Bvh::new(self.placed_objects())));
+        world
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sphere::Sphere, Color, Lambertian, Point3, Ray, Vec3};
+
+    #[test]
+    fn scene_graph_test() {
+        let ball = Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            0.5,
+            Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+        ));
+        let mut graph = SceneGraph::new();
+        let car =
+            SceneNode::new("car").with_transform(Transform::translation(Vec3::new(0.0, 0.0, -5.0)));
+        graph.add(car, None).unwrap();
+        for (name, x) in [("left wheel", -1.0), ("right wheel", 1.0)] {
+            let wheel = SceneNode::new(name)
+                .with_transform(Transform::translation(Vec3::new(x, 0.0, 0.0)))
+                .with_object(ball.clone());
+            graph.add(wheel, Some("car")).unwrap();
+        }
+        graph
+            .add(SceneNode::new("hubcap"), Some("left wheel"))
+            .unwrap();
+        assert_eq!(graph.len(), 4);
+        assert_eq!(graph.children("car"), ["left wheel", "right wheel"]);
+        assert_eq!(graph.parent("hubcap"), Some("left wheel"));
+        assert_eq!(
+            graph.add(SceneNode::new("car"), None),
+            Err(SceneError::DuplicateName("car".to_string()))
+        );
+        assert_eq!(
+            graph.add(SceneNode::new("door"), Some("truck")),
+            Err(SceneError::UnknownNode("truck".to_string()))
+        );
+
+        let transform = graph.world_transform("hubcap").unwrap();
+        let origin = transform.point(&Point3::new(0.0, 0.0, 0.0));
+        assert!((origin - Point3::new(-1.0, 0.0, -5.0)).norm() < 1e-9);
+
+        let ray = Ray::new(Point3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = graph.flatten().hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 4.5).abs() < 1e-9);
+
+        // Moving the car moves both wheels.
+        graph.node_mut("car").unwrap().transform =
+            Transform::translation(Vec3::new(0.0, 0.0, -3.0));
+        let rec = graph.flatten().hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 2.5).abs() < 1e-9);
+
+        let spare = SceneNode::new("spare wheel").with_object(ball);
+        let replaced = graph.replace("left wheel", spare).unwrap();
+        assert_eq!(replaced.name(), "left wheel");
+        assert_eq!(graph.children("car"), ["spare wheel", "right wheel"]);
+        assert_eq!(graph.parent("hubcap"), Some("spare wheel"));
+        assert!(graph.node("left wheel").is_none());
+
+        graph.remove("spare wheel").unwrap();
+        assert!(graph.node("hubcap").is_none());
+        assert_eq!(graph.children("car"), ["right wheel"]);
+        assert_eq!(graph.placed_objects().len(), 1);
+        assert!(graph.remove("spare wheel").is_err());
+        graph.remove("car").unwrap();
+        assert!(graph.is_empty() && graph.roots().is_empty());
+    }
+}
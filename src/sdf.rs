@@ -0,0 +1,373 @@
+use std::sync::Arc;
+
+use crate::{HitRecord, Hittable, Material, Point3, Ray, Vec3};
+
+/// Defines a surface by its signed distance function: the distance from any point to the closest
+/// point of the surface, negative inside. Functions that only bound the distance from below also
+/// work, at the cost of smaller steps.
+pub trait Sdf: Sync + Send {
+    /// Returns the signed distance from `p` to the surface.
+    fn distance(&self, p: &Point3) -> f64;
+}
+
+impl<F: Fn(&Point3) -> f64 + Sync + Send> Sdf for F {
+    fn distance(&self, p: &Point3) -> f64 {
+        self(p)
+    }
+}
+
+/// Defines a sphere as a distance field.
+pub struct SphereSdf {
+    center: Point3,
+    radius: f64,
+}
+
+impl SphereSdf {
+    pub fn new(center: Point3, radius: f64) -> Self {
+        Self { center, radius }
+    }
+}
+
+impl Sdf for SphereSdf {
+    fn distance(&self, p: &Point3) -> f64 {
+        (p - self.center).norm() - self.radius
+    }
+}
+
+/// Defines an axis aligned box with edges rounded off by `rounding`, as a distance field.
+pub struct BoxSdf {
+    center: Point3,
+    half_extents: Vec3,
+    rounding: f64,
+}
+
+impl BoxSdf {
+    /// Creates the box centred on `center` spanning `half_extents` on either side, rounding
+    /// included.
+    pub fn new(center: Point3, half_extents: Vec3, rounding: f64) -> Self {
+        Self {
+            center,
+            half_extents,
+            rounding,
+        }
+    }
+}
+
+impl Sdf for BoxSdf {
+    fn distance(&self, p: &Point3) -> f64 {
+        let q = (p - self.center).abs() - self.half_extents.add_scalar(-self.rounding);
+        q.map(|c| c.max(0.0)).norm() + q.max().min(0.0) - self.rounding
+    }
+}
+
+/// Defines a torus around the Y axis as a distance field.
+pub struct TorusSdf {
+    center: Point3,
+    major_radius: f64,
+    minor_radius: f64,
+}
+
+impl TorusSdf {
+    pub fn new(center: Point3, major_radius: f64, minor_radius: f64) -> Self {
+        Self {
+            center,
+            major_radius,
+            minor_radius,
+        }
+    }
+}
+
+impl Sdf for TorusSdf {
+    fn distance(&self, p: &Point3) -> f64 {
+        let p = p - self.center;
+        (p.x.hypot(p.z) - self.major_radius).hypot(p.y) - self.minor_radius
+    }
+}
+
+/// Defines the Mandelbulb fractal, through its distance estimator.
+pub struct Mandelbulb {
+    center: Point3,
+    scale: f64,
+    power: f64,
+    iterations: usize,
+}
+
+impl Mandelbulb {
+    /// Creates the usual power 8 Mandelbulb, about `scale` in radius.
+    pub fn new(center: Point3, scale: f64) -> Self {
+        Self {
+            center,
+            scale,
+            power: 8.0,
+            iterations: 12,
+        }
+    }
+
+    /// Used to change the power of the iterated function, which sets the number of bulbs.
+    pub fn with_power(mut self, power: f64) -> Self {
+        self.power = power;
+        self
+    }
+
+    /// Used to change how many times the function is iterated, which sets the level of detail.
+    pub fn with_iterations(mut self, iterations: usize) -> Self {
+        self.iterations = iterations;
+        self
+    }
+}
+
+impl Sdf for Mandelbulb {
+    fn distance(&self, p: &Point3) -> f64 {
+        let c = (p - self.center) / self.scale;
+        // The estimator overshoots far away, where the bounding sphere is a safer bound.
+        if c.norm() > 2.0 {
+            return (c.norm() - 1.5) * self.scale;
+        }
+        let (mut z, mut dr, mut r) = (c, 1.0, c.norm());
+        for _ in 0..self.iterations {
+            r = z.norm();
+            if r > 2.0 {
+                break;
+            }
+            let theta = (z.z / r).acos() * self.power;
+            let phi = z.y.atan2(z.x) * self.power;
+            dr = r.powf(self.power - 1.0) * self.power * dr + 1.0;
+            z = r.powf(self.power)
+                * Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                )
+                + c;
+        }
+        0.5 * r.ln() * r / dr * self.scale
+    }
+}
+
+/// Defines the union of two distance fields with the seam smoothed over a distance of about
+/// `smoothness`, following Quilez's polynomial smooth minimum.
+pub struct SmoothUnion {
+    first: Arc<dyn Sdf>,
+    second: Arc<dyn Sdf>,
+    smoothness: f64,
+}
+
+impl SmoothUnion {
+    pub fn new(first: Arc<dyn Sdf>, second: Arc<dyn Sdf>, smoothness: f64) -> Self {
+        Self {
+            first,
+            second,
+            smoothness,
+        }
+    }
+}
+
+impl Sdf for SmoothUnion {
+    fn distance(&self, p: &Point3) -> f64 {
+        let (a, b) = (self.first.distance(p), self.second.distance(p));
+        if self.smoothness <= 0.0 {
+            return a.min(b);
+        }
+        let h = (0.5 + 0.5 * (b - a) / self.smoothness).clamp(0.0, 1.0);
+        b + (a - b) * h - self.smoothness * h * (1.0 - h)
+    }
+}
+
+/// Defines infinitely many copies of a distance field, laid out on a grid of cells of size
+/// `period` centred on the origin. A zero component leaves that axis unrepeated. The field should
+/// fit within the cell at the origin.
+pub struct Repeat {
+    sdf: Arc<dyn Sdf>,
+    period: Vec3,
+}
+
+impl Repeat {
+    pub fn new(sdf: Arc<dyn Sdf>, period: Vec3) -> Self {
+        Self { sdf, period }
+    }
+}
+
+impl Sdf for Repeat {
+    fn distance(&self, p: &Point3) -> f64 {
+        let q = p.zip_map(&self.period, |c, period| {
+            if period > 0.0 {
+                c - period * (c / period).round()
+            } else {
+                c
+            }
+        });
+        self.sdf.distance(&q)
+    }
+}
+
+/// Defines a distance field twisted around the Y axis by `rate` radians per unit of height.
+/// Twisting stretches distances, so the resulting field needs a
+/// [step scale](SdfHittable::with_step_scale) of about 1 / sqrt(1 + (rate · radius)²) for a field
+/// reaching `radius` from the axis.
+pub struct Twist {
+    sdf: Arc<dyn Sdf>,
+    rate: f64,
+}
+
+impl Twist {
+    pub fn new(sdf: Arc<dyn Sdf>, rate: f64) -> Self {
+        Self { sdf, rate }
+    }
+}
+
+impl Sdf for Twist {
+    fn distance(&self, p: &Point3) -> f64 {
+        let (sin, cos) = (self.rate * p.y).sin_cos();
+        let q = Point3::new(cos * p.x + sin * p.z, p.y, -sin * p.x + cos * p.z);
+        self.sdf.distance(&q)
+    }
+}
+
+/// Defines a [Hittable] surface found by sphere tracing a distance field: stepping along the ray
+/// by the distance to the surface until it gets close enough.
+pub struct SdfHittable {
+    sdf: Arc<dyn Sdf>,
+    material: Arc<dyn Material>,
+    epsilon: f64,
+    max_steps: usize,
+    max_distance: f64,
+    step_scale: f64,
+}
+
+impl SdfHittable {
+    pub fn new(sdf: Arc<dyn Sdf>, material: Arc<dyn Material>) -> Self {
+        Self {
+            sdf,
+            material,
+            epsilon: 1e-4,
+            max_steps: 512,
+            max_distance: 1000.0,
+            step_scale: 1.0,
+        }
+    }
+
+    /// Used to set how close to the surface a point has to be to count as a hit.
+    pub fn with_epsilon(mut self, epsilon: f64) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+
+    /// Used to set how many steps are taken along a ray before giving up.
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Used to set how far from the origin of a ray, in either direction, the surface is looked
+    /// for.
+    pub fn with_max_distance(mut self, max_distance: f64) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+
+    /// Used to shorten every step, for fields that overestimate the distance to their surface.
+    pub fn with_step_scale(mut self, step_scale: f64) -> Self {
+        self.step_scale = step_scale;
+        self
+    }
+
+    /// Returns the gradient of the field at `p`, estimated from four samples around it.
+    fn gradient(&self, p: &Point3) -> Vec3 {
+        let h = self.epsilon;
+        [
+            Vec3::new(1.0, -1.0, -1.0),
+            Vec3::new(-1.0, -1.0, 1.0),
+            Vec3::new(-1.0, 1.0, -1.0),
+            Vec3::new(1.0, 1.0, 1.0),
+        ]
+        .iter()
+        .map(|k| k * self.sdf.distance(&(p + h * k)))
+        .sum()
+    }
+}
+
+impl Hittable for SdfHittable {
+    /// Provides a definition of hit() for distance fields, from either side of the surface.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let length = ray.direction().norm();
+        let direction = ray.direction() / length;
+        // Distances along the unit direction.
+        let mut s = (t_min * length).max(-self.max_distance);
+        let s_max = (t_max * length).min(self.max_distance);
+
+        // The side of the surface the ray travels on. A ray leaving the surface, such as a
+        // scattered one, is stepped away from it before looking for hits.
+        let start = ray.origin() + s * direction;
+        let distance = self.sdf.distance(&start);
+        let mut leaving = distance.abs() < self.epsilon;
+        let side = if leaving {
+            self.gradient(&start).dot(&direction).signum()
+        } else {
+            distance.signum()
+        };
+
+        for _ in 0..self.max_steps {
+            if s > s_max {
+                return None;
+            }
+            let p = ray.origin() + s * direction;
+            let distance = side * self.sdf.distance(&p);
+            if leaving {
+                leaving = distance < self.epsilon;
+            } else if distance < self.epsilon {
+                let t = s / length;
+                let outward_normal = self.gradient(&p).normalize();
+                return Some(HitRecord::new(
+                    ray,
+                    t,
+                    &outward_normal,
+                    self.material.clone(),
+                ));
+            }
+            s += (distance * self.step_scale).max(self.epsilon);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+
+    #[test]
+    fn sphere_tracing_test() {
+        let material = Arc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
+        let sphere = SdfHittable::new(
+            Arc::new(SphereSdf::new(Point3::new(0.0, 0.0, 0.0), 1.0)),
+            material,
+        );
+        let ray = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0));
+
+        let rec = sphere.hit(&ray, 0.001, f64::MAX).unwrap();
+        assert!((rec.t - 2.0).abs() < 1e-3);
+        assert!(rec.front_face);
+        assert!((rec.normal - Vec3::new(-1.0, 0.0, 0.0)).norm() < 1e-3);
+
+        // Leaving from the surface, towards the inside.
+        let inside = Ray::new(rec.point, Vec3::new(1.0, 0.0, 0.0));
+        let rec = sphere.hit(&inside, 0.0, f64::MAX).unwrap();
+        assert!((rec.t - 2.0).abs() < 1e-3);
+        assert!(!rec.front_face);
+    }
+
+    #[test]
+    fn operators_test() {
+        let a = Arc::new(SphereSdf::new(Point3::new(-1.0, 0.0, 0.0), 1.0));
+        let b = Arc::new(SphereSdf::new(Point3::new(1.0, 0.0, 0.0), 1.0));
+        let origin = Point3::new(0.0, 0.0, 0.0);
+
+        assert_eq!(
+            SmoothUnion::new(a.clone(), b.clone(), 0.0).distance(&origin),
+            0.0
+        );
+        assert!(SmoothUnion::new(a.clone(), b, 0.5).distance(&origin) < 0.0);
+        let repeated = Repeat::new(a, Vec3::new(4.0, 0.0, 0.0));
+        assert!((repeated.distance(&Point3::new(6.0, 0.0, 0.0))).abs() < 1e-12);
+    }
+}
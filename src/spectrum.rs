@@ -0,0 +1,123 @@
+use std::sync::OnceLock;
+
+use crate::{definitions::sample_1d, Color, Vec3};
+
+/// Shortest wavelength, in nanometres, sampled by the spectral renderer.
+pub const LAMBDA_MIN: f64 = 380.0;
+
+/// Longest wavelength, in nanometres, sampled by the spectral renderer.
+pub const LAMBDA_MAX: f64 = 780.0;
+
+/// Provides logic for picking a uniformly distributed wavelength in the visible range, drawn from
+/// the current sample.
+pub fn sample_wavelength() -> f64 {
+    LAMBDA_MIN + sample_1d() * (LAMBDA_MAX - LAMBDA_MIN)
+}
+
+/// Piecewise gaussian used by the analytic fit of the CIE colour matching functions.
+fn lobe(lambda: f64, mu: f64, sigma_low: f64, sigma_high: f64) -> f64 {
+    let sigma = if lambda < mu { sigma_low } else { sigma_high };
+    (-0.5 * ((lambda - mu) / sigma).powi(2)).exp()
+}
+
+/// Returns the CIE 1931 2° colour matching functions at `lambda` nanometres, using the multi-lobe
+/// fit from Wyman, Sloan and Shirley (2013).
+pub fn cie_xyz(lambda: f64) -> Vec3 {
+    Vec3::new(
+        1.056 * lobe(lambda, 599.8, 37.9, 31.0) + 0.362 * lobe(lambda, 442.0, 16.0, 26.7)
+            - 0.065 * lobe(lambda, 501.1, 20.4, 26.2),
+        0.821 * lobe(lambda, 568.8, 46.9, 40.5) + 0.286 * lobe(lambda, 530.9, 16.3, 31.1),
+        1.217 * lobe(lambda, 437.0, 11.8, 36.0) + 0.681 * lobe(lambda, 459.0, 26.0, 13.8),
+    )
+}
+
+/// Converts a CIE XYZ tristimulus value to linear sRGB.
+pub fn xyz_to_rgb(xyz: &Vec3) -> Color {
+    Color::new(
+        3.2406 * xyz.x - 1.5372 * xyz.y - 0.4986 * xyz.z,
+        -0.9689 * xyz.x + 1.8758 * xyz.y + 0.0415 * xyz.z,
+        0.0557 * xyz.x - 0.2040 * xyz.y + 1.0570 * xyz.z,
+    )
+}
+
+/// Per-channel average of the RGB response over the visible range, used to keep white white.
+fn white_balance() -> Color {
+    static WHITE: OnceLock<Color> = OnceLock::new();
+    *WHITE.get_or_init(|| {
+        let steps = 4000;
+        let step = (LAMBDA_MAX - LAMBDA_MIN) / steps as f64;
+        (0..steps)
+            .map(|i| xyz_to_rgb(&cie_xyz(LAMBDA_MIN + (i as f64 + 0.5) * step)))
+            .sum::<Color>()
+            / steps as f64
+    })
+}
+
+/// Returns the RGB weight of a single uniformly sampled wavelength. Averaging it over many samples
+/// converges to white, so a spectrally flat path keeps the colour it would have in RGB mode.
+pub fn wavelength_to_rgb(lambda: f64) -> Color {
+    xyz_to_rgb(&cie_xyz(lambda)).component_div(&white_balance())
+}
+
+/// Defines a wavelength dependent index of refraction, with wavelengths given in nanometres.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dispersion {
+    /// Cauchy's equation `n = a + b / λ²`, with `λ` in micrometres.
+    Cauchy { a: f64, b: f64 },
+    /// Sellmeier's equation `n² = 1 + Σ bᵢλ² / (λ² - cᵢ)`, with `λ` in micrometres.
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl Dispersion {
+    /// Schott N-BK7 borosilicate crown glass.
+    pub const BK7: Dispersion = Dispersion::Sellmeier {
+        b: [1.03961212, 0.231792344, 1.01046945],
+        c: [0.00600069867, 0.0200179144, 103.560653],
+    };
+
+    /// Diamond, strongly dispersive.
+    pub const DIAMOND: Dispersion = Dispersion::Sellmeier {
+        b: [0.3306, 4.3356, 0.0],
+        c: [0.030625, 0.011236, 0.0],
+    };
+
+    /// Wavelength of the Fraunhofer d line, at which a material's nominal index is usually quoted.
+    pub const D_LINE: f64 = 587.56;
+
+    /// Returns the index of refraction at `lambda` nanometres.
+    pub fn refractive_index(&self, lambda: f64) -> f64 {
+        let micrometres = lambda / 1000.0;
+        let l2 = micrometres.powi(2);
+        match self {
+            Dispersion::Cauchy { a, b } => a + b / l2,
+            Dispersion::Sellmeier { b, c } => (1.0
+                + b.iter().zip(c.iter()).map(|(b, c)| b * l2 / (l2 - c)).sum::<f64>())
+            .sqrt(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn white_balance_test() {
+        let steps = 1000;
+        let step = (LAMBDA_MAX - LAMBDA_MIN) / steps as f64;
+        let average = (0..steps)
+            .map(|i| wavelength_to_rgb(LAMBDA_MIN + (i as f64 + 0.5) * step))
+            .sum::<Color>()
+            / steps as f64;
+
+        assert!((average - Color::new(1.0, 1.0, 1.0)).norm() < 1e-3);
+    }
+
+    #[test]
+    fn dispersion_test() {
+        let bk7 = Dispersion::BK7;
+
+        assert!((bk7.refractive_index(Dispersion::D_LINE) - 1.5168).abs() < 1e-4);
+        assert!(bk7.refractive_index(450.0) > bk7.refractive_index(650.0));
+    }
+}
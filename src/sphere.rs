@@ -1,6 +1,9 @@
 use std::sync::Arc;
 
-use crate::{HitRecord, Hittable, Material, Point3, Ray, Vec3};
+use crate::{
+    aabb::Aabb, definitions::PI, hittable::Interval, Color, HitRecord, Hittable, Material, Point3,
+    Ray, Vec3,
+};
 
 /// Defines a geometrically Spherical object.
 pub struct Sphere {
@@ -17,6 +20,56 @@ impl Sphere {
             material,
         }
     }
+
+    /// Returns the texture coordinates of a point given by its outward normal, `u` going around the
+    /// Y axis from X = -1 and `v` going from the bottom pole to the top one.
+    fn uv(outward_normal: &Vec3) -> (f64, f64) {
+        let theta = (-outward_normal.y).acos();
+        let phi = (-outward_normal.z).atan2(outward_normal.x) + PI;
+        (phi / (2.0 * PI), theta / PI)
+    }
+
+    /// Returns the derivatives of the surface point with respect to `u` and `v`, which vanish at
+    /// the poles.
+    fn partial_derivatives(&self, outward_normal: &Vec3) -> (Vec3, Vec3) {
+        let n = outward_normal;
+        let sin_theta = (n.x.powi(2) + n.z.powi(2)).sqrt();
+        let dpdu = 2.0 * PI * self.radius * Vec3::new(n.z, 0.0, -n.x);
+        if sin_theta < 1e-8 {
+            return (dpdu, Vec3::new(0.0, 0.0, 0.0));
+        }
+        let dpdv =
+            PI * self.radius * Vec3::new(-n.y * n.x / sin_theta, sin_theta, -n.y * n.z / sin_theta);
+        (dpdu, dpdv)
+    }
+
+    /// Returns the record of `ray` hitting the sphere at `t`.
+    fn record(&self, ray: &Ray, t: f64) -> HitRecord {
+        let point = ray.at(t);
+        let outward_normal = (point - self.center) / self.radius;
+        let (u, v) = Sphere::uv(&outward_normal);
+        let (dpdu, dpdv) = self.partial_derivatives(&outward_normal);
+
+        let mut rec = HitRecord {
+            point,
+            normal: Vec3::new(0.0, 0.0, 0.0),
+            t,
+            u,
+            v,
+            dpdu,
+            dpdv,
+            front_face: false,
+            material: self.material.clone(),
+            color: Color::new(1.0, 1.0, 1.0),
+            width: 0.0,
+            object_id: 0,
+        };
+
+        rec.set_face_normal(ray, &outward_normal);
+        rec.material = self.material.clone();
+
+        rec
+    }
 }
 
 impl Hittable for Sphere {
@@ -44,21 +97,29 @@ impl Hittable for Sphere {
             }
         }
 
-        let t = root;
-        let point = ray.at(t);
-
-        let mut rec = HitRecord {
-            point,
-            normal: Vec3::new(0.0, 0.0, 0.0),
-            t,
-            front_face: false,
-            material: self.material.clone(),
-        };
+        Some(self.record(ray, root))
+    }
 
-        let outward_normal = (point - self.center) / self.radius;
-        rec.set_face_normal(ray, &outward_normal);
-        rec.material = self.material.clone();
+    fn intervals(&self, ray: &Ray) -> Vec<Interval> {
+        let oc = ray.origin() - self.center;
+        let (a, half_b, c) = (
+            ray.direction().dot(&ray.direction()),
+            oc.dot(&ray.direction()),
+            oc.dot(&oc) - self.radius.powi(2),
+        );
+        let discriminant = half_b.powi(2) - a * c;
+        if discriminant <= 0.0 {
+            return vec![];
+        }
+        let sqrtd = discriminant.sqrt();
+        vec![Interval {
+            enter: self.record(ray, (-half_b - sqrtd) / a),
+            exit: self.record(ray, (-half_b + sqrtd) / a),
+        }]
+    }
 
-        Some(rec)
+    fn bounding_box(&self) -> Option<Aabb> {
+        let radius = Vec3::repeat(self.radius.abs());
+        Some(Aabb::new(self.center - radius, self.center + radius))
     }
 }
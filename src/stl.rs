@@ -0,0 +1,203 @@
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    fmt,
+    io::{self, Read},
+};
+
+use crate::{mesh::Mesh, Point3};
+
+/// Defines what can go wrong reading an STL file.
+#[derive(Debug)]
+pub enum StlError {
+    Io(io::Error),
+    /// An ASCII file with something other than the expected keyword or number on a line.
+    Syntax {
+        line: usize,
+        message: String,
+    },
+    /// A binary file shorter than the number of triangles it announces, or a text file that is not
+    /// valid UTF-8.
+    Truncated {
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for StlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StlError::Io(error) => write!(f, "{}", error),
+            StlError::Syntax { line, message } => {
+                write!(f, "invalid STL at line {}: {}", line, message)
+            }
+            StlError::Truncated { expected, found } => write!(
+                f,
+                "truncated STL: expected {} bytes, found {}",
+                expected, found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for StlError {}
+
+/// Size of the header and triangle count of binary files.
+const HEADER_SIZE: usize = 84;
+/// Size of a triangle in binary files: a normal, three vertices and two attribute bytes.
+const TRIANGLE_SIZE: usize = 50;
+
+/// Reads an STL file, binary or ASCII, as a mesh of triangles. Vertices at the same position are
+/// merged, so that the triangles share them. The normals of the file are left out, faces being
+/// wound counterclockwise as the format requires.
+pub fn read_stl(mut reader: impl Read) -> Result<Mesh, StlError> {
+    let mut bytes = vec![];
+    reader.read_to_end(&mut bytes).map_err(StlError::Io)?;
+
+    // Binary files may also start with "solid", their size or their bytes tell them apart. Some
+    // exporters pad binary files, which are read up to the triangles they announce.
+    let expected = bytes.get(80..HEADER_SIZE).map(|count| {
+        HEADER_SIZE + TRIANGLE_SIZE * u32::from_le_bytes(count.try_into().unwrap()) as usize
+    });
+    let text = if expected != Some(bytes.len()) && bytes.starts_with(b"solid") {
+        std::str::from_utf8(&bytes).ok()
+    } else {
+        None
+    };
+    let triangles = if let Some(text) = text {
+        read_ascii(text)?
+    } else {
+        let expected = expected.unwrap_or(HEADER_SIZE);
+        if bytes.len() < expected {
+            return Err(StlError::Truncated {
+                expected,
+                found: bytes.len(),
+            });
+        }
+        bytes[HEADER_SIZE..expected]
+            .chunks_exact(TRIANGLE_SIZE)
+            .map(|triangle| {
+                let value = |i: usize| {
+                    let start = 12 + 4 * i;
+                    f32::from_le_bytes(triangle[start..start + 4].try_into().unwrap()) as f64
+                };
+                [0, 1, 2].map(|v| Point3::new(value(3 * v), value(3 * v + 1), value(3 * v + 2)))
+            })
+            .collect()
+    };
+
+    let mut index = HashMap::new();
+    let mut positions = vec![];
+    let faces = triangles
+        .iter()
+        .map(|triangle| {
+            triangle
+                .iter()
+                .map(|p| {
+                    // Adding zero turns -0 into 0, which would otherwise not match.
+                    let key = [p.x, p.y, p.z].map(|c| (c + 0.0).to_bits());
+                    *index.entry(key).or_insert_with(|| {
+                        positions.push(*p);
+                        positions.len() - 1
+                    })
+                })
+                .collect()
+        })
+        .collect();
+    Ok(Mesh::new(positions, faces))
+}
+
+/// Returns the triangles of an ASCII STL file.
+fn read_ascii(text: &str) -> Result<Vec<[Point3; 3]>, StlError> {
+    let mut triangles = vec![];
+    let mut vertices = vec![];
+    for (number, line) in text.lines().enumerate() {
+        let syntax = |message: String| StlError::Syntax {
+            line: number + 1,
+            message,
+        };
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        match words.as_slice() {
+            ["vertex", x, y, z] => {
+                let coordinates = [x, y, z]
+                    .iter()
+                    .map(|c| c.parse::<f64>())
+                    .collect::<Result<Vec<f64>, _>>()
+                    .map_err(|error| syntax(error.to_string()))?;
+                vertices.push(Point3::new(coordinates[0], coordinates[1], coordinates[2]));
+            }
+            ["endfacet", ..] => {
+                let triangle = vertices
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| syntax(format!("a facet with {} vertices", vertices.len())))?;
+                triangles.push(triangle);
+                vertices.clear();
+            }
+            ["solid", ..]
+            | ["endsolid", ..]
+            | ["facet", ..]
+            | ["outer", "loop"]
+            | ["endloop"]
+            | [] => (),
+            _ => return Err(syntax(format!("unexpected line {}", line.trim()))),
+        }
+    }
+    Ok(triangles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_stl_test() {
+        let ascii = "solid square\n\
+                     facet normal 0 0 1\nouter loop\n\
+                     vertex 0 0 0\nvertex 1 0 0\nvertex 1 1 0\nendloop\nendfacet\n\
+                     facet normal 0 0 1\nouter loop\n\
+                     vertex 0 0 0\nvertex 1 1 0\nvertex 0 1 0\nendloop\nendfacet\n\
+                     endsolid square\n";
+        let mesh = read_stl(ascii.as_bytes()).unwrap();
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.faces, vec![vec![0, 1, 2], vec![0, 2, 3]]);
+
+        // The same square in binary, with a header that looks like text.
+        let mut binary = b"solid but binary".to_vec();
+        binary.resize(80, 0);
+        binary.extend(2u32.to_le_bytes());
+        for triangle in [
+            [[0f32, 0.0], [1.0, 0.0], [1.0, 1.0]],
+            [[0.0, 0.0], [1.0, 1.0], [0.0, 1.0]],
+        ] {
+            binary.extend([0f32, 0.0, 1.0].iter().flat_map(|c| c.to_le_bytes()));
+            for [x, y] in triangle {
+                binary.extend([x, y, 0.0].iter().flat_map(|c| c.to_le_bytes()));
+            }
+            binary.extend([0, 0]);
+        }
+        let binary_mesh = read_stl(binary.as_slice()).unwrap();
+        assert_eq!(binary_mesh.positions, mesh.positions);
+        assert_eq!(binary_mesh.faces, mesh.faces);
+        let mut padded = binary.clone();
+        padded.extend([0; 16]);
+        assert_eq!(read_stl(padded.as_slice()).unwrap().faces, mesh.faces);
+
+        // A cut binary file is not mistaken for text because of its header.
+        assert!(matches!(
+            read_stl(&binary[..100]),
+            Err(StlError::Truncated {
+                expected: 184,
+                found: 100
+            })
+        ));
+        assert!(matches!(
+            read_stl(&binary[20..100]),
+            Err(StlError::Truncated { .. })
+        ));
+        assert!(matches!(
+            read_stl(ascii.replace("vertex 0 1 0", "vertex 0 one 0").as_bytes()),
+            Err(StlError::Syntax { line: 13, .. })
+        ));
+    }
+}
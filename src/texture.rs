@@ -0,0 +1,188 @@
+use crate::{perlin::Perlin, Color, Point3};
+
+/// Defines the interface of a colour that varies over the surface of an object.
+pub trait Texture: Sync + Send {
+    /// Returns the colour at the surface coordinates (`u`, `v`) of the hit `point`.
+    fn value(&self, u: f64, v: f64, point: &Point3) -> Color;
+}
+
+/// Defines a texture of a single uniform colour.
+pub struct SolidColor {
+    color: Color,
+}
+
+impl SolidColor {
+    pub fn new(color: Color) -> Self {
+        Self { color }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _point: &Point3) -> Color {
+        self.color
+    }
+}
+
+/// Defines a grey texture of smooth Perlin noise, `scale` setting its frequency.
+pub struct NoiseTexture {
+    noise: Perlin,
+    scale: f64,
+}
+
+impl NoiseTexture {
+    pub fn new(scale: f64) -> Self {
+        Self {
+            noise: Perlin::new(),
+            scale,
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f64, _v: f64, point: &Point3) -> Color {
+        Color::new(1.0, 1.0, 1.0) * 0.5 * (1.0 + self.noise.noise(&(self.scale * point)))
+    }
+}
+
+/// Defines a grey texture of turbulence, the sum of several octaves of noise.
+pub struct TurbulenceTexture {
+    noise: Perlin,
+    scale: f64,
+    depth: u32,
+}
+
+impl TurbulenceTexture {
+    pub fn new(scale: f64, depth: u32) -> Self {
+        Self {
+            noise: Perlin::new(),
+            scale,
+            depth,
+        }
+    }
+}
+
+impl Texture for TurbulenceTexture {
+    fn value(&self, _u: f64, _v: f64, point: &Point3) -> Color {
+        Color::new(1.0, 1.0, 1.0) * self.noise.turbulence(&(self.scale * point), self.depth)
+    }
+}
+
+/// Defines a marble texture, stripes along the Z axis bent by turbulence.
+pub struct MarbleTexture {
+    noise: Perlin,
+    scale: f64,
+    vein: Color,
+    base: Color,
+}
+
+impl MarbleTexture {
+    /// Creates white marble with grey veins.
+    pub fn new(scale: f64) -> Self {
+        Self::with_colors(scale, Color::new(0.1, 0.1, 0.1), Color::new(1.0, 1.0, 1.0))
+    }
+
+    pub fn with_colors(scale: f64, vein: Color, base: Color) -> Self {
+        Self {
+            noise: Perlin::new(),
+            scale,
+            vein,
+            base,
+        }
+    }
+}
+
+impl Texture for MarbleTexture {
+    fn value(&self, _u: f64, _v: f64, point: &Point3) -> Color {
+        let t = 0.5 * (1.0 + (self.scale * point.z + 10.0 * self.noise.turbulence(point, 7)).sin());
+        (1.0 - t) * self.vein + t * self.base
+    }
+}
+
+/// Defines a wood texture, growth rings around the Y axis disturbed by turbulence.
+pub struct WoodTexture {
+    noise: Perlin,
+    scale: f64,
+    light: Color,
+    dark: Color,
+}
+
+impl WoodTexture {
+    /// Creates wood with `scale` rings per unit distance from the Y axis.
+    pub fn new(scale: f64, light: Color, dark: Color) -> Self {
+        Self {
+            noise: Perlin::new(),
+            scale,
+            light,
+            dark,
+        }
+    }
+}
+
+impl Texture for WoodTexture {
+    fn value(&self, _u: f64, _v: f64, point: &Point3) -> Color {
+        let radius = (point.x.powi(2) + point.z.powi(2)).sqrt();
+        let rings = self.scale * radius + 2.0 * self.noise.turbulence(point, 4);
+        let t = (rings - rings.floor()).powi(3);
+        (1.0 - t) * self.light + t * self.dark
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definitions::seed_random;
+
+    /// Returns the values of textures built by `make` from the same seed at a grid of points, and
+    /// checks that both copies agree.
+    fn seeded_values<T: Texture>(make: impl Fn() -> T) -> Vec<Color> {
+        seed_random(7);
+        let first = make();
+        seed_random(7);
+        let second = make();
+
+        let points = (0..64).map(|i| {
+            let i = i as f64;
+            Point3::new(0.37 * i - 5.0, 0.23 * i - 3.0, 1.3 - 0.11 * i)
+        });
+        points
+            .map(|point| {
+                let value = first.value(0.0, 0.0, &point);
+                assert_eq!(value, second.value(0.0, 0.0, &point));
+                value
+            })
+            .collect()
+    }
+
+    /// Returns whether every component of `value` lies between those of `a` and `b`.
+    fn between(value: &Color, a: Color, b: Color) -> bool {
+        (0..3).all(|i| value[i] >= a[i].min(b[i]) - 1e-12 && value[i] <= a[i].max(b[i]) + 1e-12)
+    }
+
+    #[test]
+    fn turbulence_test() {
+        let values = seeded_values(|| TurbulenceTexture::new(4.0, 7));
+        // The octaves halve in weight, so the sum of their magnitudes stays below 2.
+        assert!(values.iter().all(|value| value.x == value.y
+            && value.y == value.z
+            && (0.0..2.0).contains(&value.x)));
+        assert!(values.iter().any(|value| value.x > 0.0));
+    }
+
+    #[test]
+    fn marble_test() {
+        let vein = Color::new(0.1, 0.2, 0.3);
+        let base = Color::new(0.9, 0.8, 0.7);
+        let values = seeded_values(|| MarbleTexture::with_colors(4.0, vein, base));
+        assert!(values.iter().all(|value| between(value, vein, base)));
+        assert!(values.iter().any(|value| value != &values[0]));
+    }
+
+    #[test]
+    fn wood_test() {
+        let light = Color::new(0.8, 0.6, 0.4);
+        let dark = Color::new(0.3, 0.2, 0.1);
+        let values = seeded_values(|| WoodTexture::new(6.0, light, dark));
+        assert!(values.iter().all(|value| between(value, light, dark)));
+        assert!(values.iter().any(|value| value != &values[0]));
+    }
+}
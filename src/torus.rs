@@ -0,0 +1,150 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    definitions::{solve_quartic, PI},
+    frame::{azimuth, Frame},
+    HitRecord, Hittable, Material, Point3, Ray, Vec3,
+};
+
+/// Defines a ring shaped surface, swept by a circle of radius `minor_radius` whose centre goes
+/// around a circle of radius `major_radius`.
+pub struct Torus {
+    frame: Frame,
+    major_radius: f64,
+    minor_radius: f64,
+    material: Arc<dyn Material>,
+}
+
+impl Torus {
+    /// Creates the torus centred on `center` and going around `axis`. Texture coordinates are the
+    /// angle around the axis, `u`, and the angle around the tube, `v`, starting from the outside.
+    pub fn new(
+        center: Point3,
+        axis: Vec3,
+        major_radius: f64,
+        minor_radius: f64,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        Self {
+            frame: Frame::new(center, axis),
+            major_radius,
+            minor_radius,
+            material,
+        }
+    }
+
+    /// Returns the distances along a local ray to every crossing of the surface, in increasing
+    /// order.
+    fn roots(&self, origin: &Point3, direction: &Vec3) -> Vec<f64> {
+        // Starting from the point of the ray closest to the centre with a unit direction keeps the
+        // coefficients well conditioned for far away rays.
+        let length = direction.norm();
+        let d = direction / length;
+        let shift = -origin.dot(&d);
+        let o = origin + shift * d;
+
+        let (r2, big_r2) = (self.minor_radius.powi(2), self.major_radius.powi(2));
+        // Expands (|p|² + R² - r²)² - 4R²(x² + y²) for p = o + sd.
+        let (f, e) = (o.dot(&d), o.dot(&o) + big_r2 - r2);
+        let roots = solve_quartic(
+            1.0,
+            4.0 * f,
+            4.0 * f * f + 2.0 * e - 4.0 * big_r2 * (d.x * d.x + d.y * d.y),
+            4.0 * e * f - 8.0 * big_r2 * (o.x * d.x + o.y * d.y),
+            e * e - 4.0 * big_r2 * (o.x * o.x + o.y * o.y),
+        );
+        roots.into_iter().map(|s| (s + shift) / length).collect()
+    }
+}
+
+impl Hittable for Torus {
+    /// Provides a definition of hit() for tori.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (origin, direction) = self.frame.ray_to_local(ray);
+        let t = self
+            .roots(&origin, &direction)
+            .into_iter()
+            .find(|t| (t_min..=t_max).contains(t))?;
+        let p = origin + t * direction;
+
+        // The normal points away from the closest point of the circle going through the tube.
+        let ring = p.x.hypot(p.y);
+        let core = Vec3::new(p.x, p.y, 0.0) * self.major_radius / ring;
+        let outward_normal = (p - core) / self.minor_radius;
+        let (phi, theta) = (
+            2.0 * PI * azimuth(&p),
+            outward_normal.z.atan2(ring - self.major_radius),
+        );
+        let v = theta.rem_euclid(2.0 * PI) / (2.0 * PI);
+
+        let dpdu = 2.0 * PI * Vec3::new(-p.y, p.x, 0.0);
+        let dpdv = 2.0
+            * PI
+            * self.minor_radius
+            * Vec3::new(
+                -theta.sin() * phi.cos(),
+                -theta.sin() * phi.sin(),
+                theta.cos(),
+            );
+        let rec = HitRecord::new(
+            ray,
+            t,
+            &self.frame.from_local(&outward_normal),
+            self.material.clone(),
+        );
+        Some(rec.with_uv(
+            (azimuth(&p), v),
+            (self.frame.from_local(&dpdu), self.frame.from_local(&dpdv)),
+        ))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let ring = Aabb::around_circle(self.frame.origin, &self.frame.normal, self.major_radius);
+        Some(ring.padded(self.minor_radius))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+
+    #[test]
+    fn quartic_test() {
+        // (t - 1)(t - 2)(t - 3)(t - 4)
+        let roots = solve_quartic(1.0, -10.0, 35.0, -50.0, 24.0);
+        assert_eq!(roots.len(), 4);
+        for (root, expected) in roots.iter().zip([1.0, 2.0, 3.0, 4.0]) {
+            assert!((root - expected).abs() < 1e-9);
+        }
+        assert!(solve_quartic(1.0, 0.0, 0.0, 0.0, 1.0).is_empty());
+        // A vanishing leading coefficient overflows rather than panicking.
+        assert!(solve_quartic(0.0, 0.0, 1.0, 0.0, -1.0)
+            .iter()
+            .all(|t| t.is_finite()));
+    }
+
+    #[test]
+    fn torus_hit_test() {
+        let material = Arc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
+        let torus = Torus::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            2.0,
+            0.5,
+            material,
+        );
+        let ray = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0));
+
+        let rec = torus.hit(&ray, 0.001, f64::MAX).unwrap();
+        assert!((rec.t - 1.25).abs() < 1e-9);
+        assert!((rec.normal - Vec3::new(-1.0, 0.0, 0.0)).norm() < 1e-9);
+        let rec = torus.hit(&ray, 1.3, f64::MAX).unwrap();
+        assert!((rec.t - 1.75).abs() < 1e-9);
+        assert!(!rec.front_face);
+
+        let miss = Ray::new(Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(torus.hit(&miss, 0.001, f64::MAX).is_none());
+    }
+}
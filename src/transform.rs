@@ -0,0 +1,220 @@
+use std::sync::Arc;
+
+use nalgebra::{Matrix3, Matrix4, Rotation3, Unit, Vector4};
+
+use crate::{
+    aabb::Aabb, definitions::degrees_to_radians, hittable::Interval, HitRecord, Hittable, Point3,
+    Ray, Vec3,
+};
+
+/// Defines an invertible affine transformation, kept along with its inverse.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    matrix: Matrix4<f64>,
+    inverse: Matrix4<f64>,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Self {
+            matrix: Matrix4::identity(),
+            inverse: Matrix4::identity(),
+        }
+    }
+
+    /// Creates the transformation of an affine `matrix`, or None if it cannot be inverted.
+    pub fn from_matrix(matrix: Matrix4<f64>) -> Option<Self> {
+        Some(Self {
+            matrix,
+            inverse: matrix.try_inverse()?,
+        })
+    }
+
+    pub fn translation(offset: Vec3) -> Self {
+        Self {
+            matrix: Matrix4::new_translation(&offset),
+            inverse: Matrix4::new_translation(&-offset),
+        }
+    }
+
+    /// Creates a rotation by `angle` degrees around `axis`, counterclockwise looking down the axis.
+    pub fn rotation(axis: Vec3, angle: f64) -> Self {
+        let rotation =
+            Rotation3::from_axis_angle(&Unit::new_normalize(axis), degrees_to_radians(angle));
+        Self {
+            matrix: rotation.to_homogeneous(),
+            inverse: rotation.inverse().to_homogeneous(),
+        }
+    }
+
+    /// Creates a scaling by a factor along each axis, none of which may be zero.
+    pub fn scaling(factors: Vec3) -> Self {
+        Self {
+            matrix: Matrix4::new_nonuniform_scaling(&factors),
+            inverse: Matrix4::new_nonuniform_scaling(&factors.map(|f| 1.0 / f)),
+        }
+    }
+
+    /// Returns the transformation applying this one and then `next`.
+    pub fn then(&self, next: &Transform) -> Transform {
+        Transform {
+            matrix: next.matrix * self.matrix,
+            inverse: self.inverse * next.inverse,
+        }
+    }
+
+    pub fn inverse(&self) -> Transform {
+        Transform {
+            matrix: self.inverse,
+            inverse: self.matrix,
+        }
+    }
+
+    pub fn matrix(&self) -> &Matrix4<f64> {
+        &self.matrix
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.matrix == Matrix4::identity()
+    }
+
+    pub fn point(&self, p: &Point3) -> Point3 {
+        (self.matrix * Vector4::new(p.x, p.y, p.z, 1.0)).xyz()
+    }
+
+    pub fn vector(&self, v: &Vec3) -> Vec3 {
+        (self.matrix * Vector4::new(v.x, v.y, v.z, 0.0)).xyz()
+    }
+
+    /// Returns the normal `n` of a transformed surface, which does not stay perpendicular to it
+    /// under plain transformation when the scaling is not uniform. The result is not normalised.
+    pub fn normal(&self, n: &Vec3) -> Vec3 {
+        let inverse = Matrix3::from_fn(|r, c| self.inverse[(r, c)]);
+        inverse.transpose() * n
+    }
+
+    pub fn ray(&self, ray: &Ray) -> Ray {
+        Ray::new(self.point(&ray.origin()), self.vector(&ray.direction()))
+            .with_wavelength(ray.wavelength())
+    }
+
+    /// Returns the box around the eight transformed corners of `bbox`.
+    pub fn bounding_box(&self, bbox: &Aabb) -> Aabb {
+        let corners = (0..8).map(|i| {
+            let corner = |axis: usize| match i >> axis & 1 {
+                0 => bbox.min[axis],
+                _ => bbox.max[axis],
+            };
+            self.point(&Point3::new(corner(0), corner(1), corner(2)))
+        });
+        Aabb::from_points(corners).unwrap()
+    }
+
+    /// Returns the record of a hit on a transformed object, from the record in object space.
+    fn hit_record(&self, rec: HitRecord) -> HitRecord {
+        // The transformed ray has the same parameter at the hit, t is kept as is.
+        let dpdv = self.vector(&rec.dpdv);
+        // Fibres span their width along dpdv, which stretches with them.
+        let width = if rec.width > 0.0 {
+            rec.width * dpdv.norm() / rec.dpdv.norm()
+        } else {
+            0.0
+        };
+        HitRecord {
+            point: self.point(&rec.point),
+            normal: self.normal(&rec.normal).normalize(),
+            dpdu: self.vector(&rec.dpdu),
+            dpdv,
+            width,
+            ..rec
+        }
+    }
+}
+
+/// Defines an object moved, rotated or scaled by a transformation, without copying it. The same
+/// object can be placed many times, as instances sharing its geometry.
+pub struct Transformed {
+    object: Arc<dyn Hittable>,
+    transform: Transform,
+}
+
+impl Transformed {
+    pub fn new(object: Arc<dyn Hittable>, transform: Transform) -> Self {
+        Self { object, transform }
+    }
+}
+
+impl Hittable for Transformed {
+    /// Provides a definition of hit() for transformed objects, hitting the object with the ray
+    /// brought back into its own space.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let local = self.transform.inverse().ray(ray);
+        self.object
+            .hit(&local, t_min, t_max)
+            .map(|rec| self.transform.hit_record(rec))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.object
+            .bounding_box()
+            .map(|bbox| self.transform.bounding_box(&bbox))
+    }
+
+    fn intervals(&self, ray: &Ray) -> Vec<Interval> {
+        let local = self.transform.inverse().ray(ray);
+        self.object
+            .intervals(&local)
+            .into_iter()
+            .map(|interval| Interval {
+                enter: self.transform.hit_record(interval.enter),
+                exit: self.transform.hit_record(interval.exit),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sphere::Sphere, Color, Lambertian};
+
+    #[test]
+    fn transformed_test() {
+        let transform = Transform::scaling(Vec3::new(2.0, 1.0, 1.0))
+            .then(&Transform::rotation(Vec3::new(0.0, 0.0, 1.0), 90.0))
+            .then(&Transform::translation(Vec3::new(0.0, 0.0, -5.0)));
+        let p = Point3::new(1.0, 0.0, 0.0);
+        assert!((transform.point(&p) - Point3::new(0.0, 2.0, -5.0)).norm() < 1e-9);
+        assert!((transform.inverse().point(&transform.point(&p)) - p).norm() < 1e-9);
+
+        // A unit sphere stretched into an ellipsoid twice as tall, after the rotation.
+        let sphere = Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+            Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+        ));
+        let ellipsoid = Transformed::new(sphere, transform);
+        let ray = Ray::new(Point3::new(0.0, 10.0, -5.0), Vec3::new(0.0, -1.0, 0.0));
+        let rec = ellipsoid.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 8.0).abs() < 1e-9);
+        assert!((rec.normal - Vec3::new(0.0, 1.0, 0.0)).norm() < 1e-9);
+        assert!(rec.front_face);
+
+        // Off the axes, the normal is not the transformed one of the sphere.
+        let ray = Ray::new(Point3::new(0.5, 10.0, -5.0), Vec3::new(0.0, -1.0, 0.0));
+        let rec = ellipsoid.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        let (x, y) = (rec.point.x, rec.point.y);
+        let gradient = Vec3::new(2.0 * x, 2.0 * y / 4.0, 0.0).normalize();
+        assert!((rec.normal - gradient).norm() < 1e-9);
+
+        let bbox = ellipsoid.bounding_box().unwrap();
+        assert!((bbox.min - Point3::new(-1.0, -2.0, -6.0)).norm() < 1e-9);
+        assert!((bbox.max - Point3::new(1.0, 2.0, -4.0)).norm() < 1e-9);
+    }
+}